@@ -5,23 +5,103 @@
 // TODO: Remove
 #![allow(dead_code, unused_variables)]
 
-use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
 use std::mem;
+use std::rc::Rc;
 
 use seed::{prelude::*, *};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use ulid::Ulid;
-use web_sys;
+use uuid::Uuid;
 
 const ENTER_KEY: &str = "Enter";
 const ESC_KEY: &str = "Escape";
+const SPACE_KEY: &str = " ";
+const TAB_KEY: &str = "Tab";
 const STORAGE_KEY: &str = "todos-seed";
+const COMPLETED_TODAY_STORAGE_KEY: &str = "todos-seed-completed-today";
+const SETTINGS_STORAGE_KEY: &str = "todos-seed-settings";
+const SYNC_VERSION_STORAGE_KEY: &str = "todos-seed-sync-version";
+const SYNC_BASE_STORAGE_KEY: &str = "todos-seed-sync-base";
+const DONE_LOG_STORAGE_KEY: &str = "todos-seed-done-log";
+const MINIMIZED_STORAGE_KEY: &str = "todos-seed-minimized";
+const STREAK_STORAGE_KEY: &str = "todos-seed-streak";
+const ACTIVITY_STORAGE_KEY: &str = "todos-seed-activity";
+const SCHEDULED_STORAGE_KEY: &str = "todos-seed-scheduled";
+const FOCUS_TASK_STORAGE_KEY: &str = "todos-seed-focus-task";
+// Keys used by other TodoMVC implementations, checked by "Import from other TodoMVC apps".
+const LEGACY_STORAGE_KEYS: &[&str] = &[
+    "todos-vanillajs",
+    "todos-vanilla-es6",
+    "todos-jquery",
+    "todos-react",
+    "todos-preact",
+    "todos-vue",
+    "todos-angularjs",
+    "todos-backbone",
+    "todos-mithril",
+];
 
 const ACTIVE: &str = "active";
 const COMPLETED: &str = "completed";
+const UPCOMING: &str = "upcoming";
+const BACKLOG: &str = "backlog";
+// `#/todo/<id>` links to a single todo.
+const TODO_LINK: &str = "todo";
+
+// Offered by the icon picker in the edit view.
+const ICONS: &[&str] = &["⭐", "🔥", "📌", "💡", "🛒", "📞", "📧", "🏠", "💼", "🎉"];
+
+// Offered by the points picker in the edit view.
+const POINTS: &[u8] = &[1, 2, 3, 5, 8, 13];
+
+const DEFAULT_BACKLOG_PULL_COUNT: usize = 3;
+// Similar todos suggested in the edit view.
+const RELATED_TODO_COUNT: usize = 3;
+// Active todos listed by the minimized widget.
+const MINI_TODO_COUNT: usize = 3;
+// Browsers usually allow ~5MB per origin, warn a bit before that.
+const STORAGE_SOFT_LIMIT_BYTES: usize = 4 * 1024 * 1024;
+
+// Has to be typed before "Delete all data" is enabled.
+const RESET_CONFIRMATION: &str = "DELETE";
+
+const NOTICE_TIMEOUT_MS: u32 = 4_000;
+// A removed todo can be restored from its toast for this long.
+const UNDO_DELETE_TIMEOUT_MS: u32 = 5_000;
+// How long "Saved" stays in the header after a change.
+const SAVED_INDICATOR_MS: u32 = 1_500;
+// Voice notes are stored with the todos, as base64 they take about 2.7× this in the storage.
+const VOICE_NOTE_MAX_SECS: u32 = 30;
+const VOICE_NOTE_MAX_BYTES: usize = 256 * 1024;
+// Changes kept by undo and by redo.
+const HISTORY_LIMIT: usize = 20;
+// Older activity entries are dropped.
+const ACTIVITY_LIMIT: usize = 200;
+const HIGHLIGHT_TIMEOUT_MS: u32 = 3_000;
+// Type-ahead starts a new search after this pause between key presses.
+const TYPE_AHEAD_RESET_MS: i64 = 1_000;
+// The second key of a sequence like `g n` has to follow the first within this time.
+const KEY_SEQUENCE_TIMEOUT_MS: i64 = 1_500;
+
+// Elements a modal's focus trap cycles through.
+const FOCUSABLE_SELECTOR: &str = "a[href], button:not([disabled]), input:not([disabled]), \
+    select:not([disabled]), textarea:not([disabled]), [tabindex]:not([tabindex=\"-1\"])";
+
+// Interactive elements the accessibility audit checks for a name.
+const AUDITED_SELECTOR: &str = "a[href], button, input, select, textarea, [tabindex]";
+// Set on elements without an accessible name while auditing, `index.css` outlines them.
+const A11Y_MISSING_ATTRIBUTE: &str = "data-a11y-missing";
+
+const POMODORO_FOCUS_SECS: u32 = 25 * 60;
+const POMODORO_BREAK_SECS: u32 = 5 * 60;
+
+const MS_PER_MINUTE: f64 = 60_000.;
+const MS_PER_DAY: f64 = 86_400_000.;
 
 // ------ ------
 //     Init
@@ -29,336 +109,7321 @@ const COMPLETED: &str = "completed";
 
 // `init` describes what should happen when your app started.
 fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
-    orders.subscribe(Msg::UrlChanged);
+    orders
+        .subscribe(Msg::UrlChanged)
+        .stream(streams::document_event(Ev::KeyDown, |event| {
+            shortcut_msg(&event.unchecked_into())
+        }))
+        .stream(streams::interval(60_000, || Msg::CheckDayRollover))
+        .stream(streams::interval(60_000, || Msg::ActivateScheduled))
+        // Fired when another tab writes to the local storage.
+        .stream(streams::window_event(Ev::Storage, |_| Msg::StorageChanged))
+        // Leaving full screen (e.g. with Escape, handled by the browser) ends the distraction shield.
+        .stream(streams::document_event(
+            Ev::from("fullscreenchange"),
+            |_| Msg::FullscreenChanged,
+        ))
+        .stream(streams::window_event(Ev::PopState, |event| {
+            let state = event.unchecked_into::<web_sys::PopStateEvent>().state();
+            Msg::RestoreHistoryState(state.as_string())
+        }));
+
+    let mut completed_today: CompletedToday =
+        LocalStorage::get(COMPLETED_TODAY_STORAGE_KEY).unwrap_or_default();
+    completed_today.roll_over(Day::today());
+
+    let settings: Settings = LocalStorage::get(SETTINGS_STORAGE_KEY).unwrap_or_default();
+    if settings.sync_url.is_some() {
+        orders.send_msg(Msg::FetchRemote);
+    }
+    let base_url = route_base_url();
+    if let Some(id) = todo_link_target(&url, &base_url) {
+        orders.send_msg(Msg::HighlightTodo(id));
+    }
+    // Todos scheduled while the app was closed.
+    orders.send_msg(Msg::ActivateScheduled);
+
+    // `?a11y=1` is a developer aid for embedders, it's off otherwise.
+    let a11y_audit = url
+        .search()
+        .get("a11y")
+        .map_or(false, |values| values.iter().any(|value| value == "1"));
+    if a11y_audit {
+        orders.after_next_render(|_| audit_accessibility());
+    }
+
+    let todos: BTreeMap<TodoId, Todo> = LocalStorage::get(STORAGE_KEY).unwrap_or_default();
 
     Model {
-        todos: LocalStorage::get(STORAGE_KEY).unwrap_or_default(),
+        saved_todos: todos.clone(),
+        todos,
         new_todo_title: "".to_string(),
+        new_todo_input: ElRef::new(),
         selected_todo: None,
-        filter: Filter::from(url),
-        base_url: Url::new(),
+        filter: resolve_initial_filter(url, &base_url, settings.default_filter),
+        previous_filter: Filter::default(),
+        base_url,
+        printing: false,
+        minimized: LocalStorage::get(MINIMIZED_STORAGE_KEY).unwrap_or_default(),
+        backlog_pull_count: DEFAULT_BACKLOG_PULL_COUNT,
+        storage_warning: None,
+        completed_today,
+        streak: LocalStorage::get(STREAK_STORAGE_KEY).unwrap_or_default(),
+        settings,
+        settings_open: false,
+        focus_trap: None,
+        pending_hides: BTreeMap::new(),
+        hidden_completed: BTreeSet::new(),
+        done_log: newest_first(LocalStorage::get(DONE_LOG_STORAGE_KEY).unwrap_or_default()),
+        pomodoro: None,
+        distraction_shield: None,
+        reset_confirmation: None,
+        notice: None,
+        highlighted_todo: None,
+        sync_version: LocalStorage::get(SYNC_VERSION_STORAGE_KEY).unwrap_or_default(),
+        sync_status: SyncStatus::Idle,
+        sync_base: LocalStorage::get(SYNC_BASE_STORAGE_KEY).ok(),
+        pushing: None,
+        conflicts: Vec::new(),
+        history: History::default(),
+        type_ahead: (String::new(), 0),
+        activity: LocalStorage::get(ACTIVITY_STORAGE_KEY).unwrap_or_default(),
+        activity_open: false,
+        scheduled: LocalStorage::get(SCHEDULED_STORAGE_KEY).unwrap_or_default(),
+        schedule_at: String::new(),
+        completed_summary_expanded: false,
+        rebinding: None,
+        key_sequence: KeySequence::default(),
+        unload_guard: None,
+        last_deleted: None,
+        save_status: SaveStatus::Idle,
+        celebrating: false,
+        a11y_audit,
+        // Yesterday's focus doesn't carry over.
+        focus_task: LocalStorage::get(FOCUS_TASK_STORAGE_KEY)
+            .ok()
+            .filter(|(_, day): &(TodoId, Day)| *day == Day::today())
+            .map(|(id, _)| id),
     }
 }
 
+// An explicit URL hash wins, otherwise the session starts on the configured default filter.
+// (There is no stored "last used" filter, so nothing sits between the two.)
+fn resolve_initial_filter(url: Url, base_url: &Url, default_filter: Filter) -> Filter {
+    let url = url.skip_hash_base_path(base_url.hash_path());
+    if url
+        .clone()
+        .remaining_hash_path_parts()
+        .iter()
+        .any(|part| not(part.is_empty()))
+    {
+        Filter::from(url)
+    } else {
+        default_filter
+    }
+}
+
+// The hash path the app's routes live under when it's embedded, e.g. `#/todos/active`
+// for `<section class="todoapp" data-route-base="todos">`. Empty by default.
+fn route_base_url() -> Url {
+    let route_base = document()
+        .get_elements_by_class_name("todoapp")
+        .item(0)
+        .and_then(|root| root.get_attribute("data-route-base"))
+        .unwrap_or_default();
+    Url::new().set_hash_path(route_base.split('/').filter(|part| not(part.is_empty())))
+}
+
+// The `#/<base>/<parts>` link of a route under the route base.
+fn hash_link(base_url: &Url, parts: &[&str]) -> String {
+    let url = parts
+        .iter()
+        .fold(base_url.clone(), |url, part| url.add_hash_path_part(*part));
+    format!("#/{}", url.hash_path().join("/"))
+}
+
 // ------ ------
 //     Model
 // ------ ------
 
 // `Model` describes our app state.
-struct Model {
-    todos: BTreeMap<Ulid, Todo>,
+pub struct Model {
+    todos: BTreeMap<TodoId, Todo>,
     new_todo_title: String,
+    new_todo_input: ElRef<web_sys::HtmlInputElement>,
     selected_todo: Option<SelectedTodo>,
     filter: Filter,
+    // Where `ToggleCompletedVisibility` returns to from `Active`.
+    previous_filter: Filter,
     base_url: Url,
+    printing: bool,
+    // Only the active count and the first few active todos are shown, e.g. in a dashboard.
+    minimized: bool,
+    backlog_pull_count: usize,
+    storage_warning: Option<String>,
+    completed_today: CompletedToday,
+    streak: Streak,
+    settings: Settings,
+    settings_open: bool,
+    // Belongs to the open modal, if any.
+    focus_trap: Option<FocusTrap>,
+    // Auto-hide timers of just completed todos, dropping a handle cancels its timer.
+    pending_hides: BTreeMap<TodoId, CmdHandle>,
+    // Completed todos left out of the All view, they still show up in Completed.
+    hidden_completed: BTreeSet<TodoId>,
+    // Work that was done without ever being a todo, as `(timestamp_ms, text)`, newest first.
+    done_log: Vec<(i64, String)>,
+    pomodoro: Option<Pomodoro>,
+    // Full screen with only today's focus, see `Msg::EnterDistractionShield`.
+    distraction_shield: Option<DistractionShield>,
+    // `Some` while the "Delete all data" confirmation is open, holds the typed text.
+    reset_confirmation: Option<String>,
+    notice: Option<Notice>,
+    // The todo a `#/todo/<id>` link pointed to, it's highlighted for a moment.
+    highlighted_todo: Option<Highlight>,
+    // Bumped on every local change, the higher version wins when syncing.
+    sync_version: u64,
+    sync_status: SyncStatus,
+    // The todos as of the last sync, the common ancestor of a three-way merge.
+    sync_base: Option<RemoteTodos>,
+    // The todos being pushed, they become the sync base once the server has them.
+    pushing: Option<RemoteTodos>,
+    // Fields both devices changed since the last sync, waiting for the user to pick a side.
+    conflicts: Vec<Conflict>,
+    // The todos as last written to the local storage, to detect local changes.
+    // A clone, so it shares the voice notes with `todos`.
+    saved_todos: BTreeMap<TodoId, Todo>,
+    // Snapshots of the todos for undo and redo.
+    history: History,
+    // Letters typed while the list has focus and when the last one was typed (ms).
+    type_ahead: (String, i64),
+    // What happened to the todos, oldest first.
+    activity: Vec<ActivityEntry>,
+    // The activity list is shown in the settings.
+    activity_open: bool,
+    // Todos to add once their timestamp (ms) has passed.
+    scheduled: BTreeMap<TodoId, (Todo, i64)>,
+    // The "Schedule for…" input, a `datetime-local` value.
+    schedule_at: String,
+    // The summarized completed todos are listed after all.
+    completed_summary_expanded: bool,
+    // The next shortcut pressed is bound to this action.
+    rebinding: Option<Rebinding>,
+    key_sequence: KeySequence,
+    // Installed while the edited title has unsaved changes.
+    unload_guard: Option<UnloadGuard>,
+    // The one todo to get done today, shown in a banner above the list.
+    focus_task: Option<TodoId>,
+    // The last todo removed with its destroy button, until its toast times out.
+    last_deleted: Option<Deleted>,
+    save_status: SaveStatus,
+    // Today's completions just reached the daily goal, until the banner is dismissed.
+    celebrating: bool,
+    // Re-run `audit_accessibility` after every render.
+    a11y_audit: bool,
 }
 
-impl Model {}
+// Feedback on writing the todos into the local storage, see `Settings::show_save_status`.
+enum SaveStatus {
+    Idle,
+    // Dropping the handle cancels the timeout, so the latest save shows for the full time.
+    Saved(Option<CmdHandle>),
+    Failed,
+}
+
+#[derive(Copy, Clone)]
+enum SaveEvent {
+    // The todos were written.
+    Written,
+    // Writing the todos failed.
+    WriteFailed,
+    // A local change was written and the indicator is enabled.
+    ChangeSaved,
+    // "Saved" has been shown for `SAVED_INDICATOR_MS`.
+    IndicatorTimedOut,
+}
+
+impl SaveStatus {
+    // A failure sticks until the todos can be written again.
+    fn next(self, event: SaveEvent) -> Self {
+        match (self, event) {
+            (_, SaveEvent::WriteFailed) => Self::Failed,
+            (Self::Failed, SaveEvent::Written) => Self::Idle,
+            (Self::Failed, SaveEvent::ChangeSaved) => Self::Failed,
+            // A new timeout comes with it, the old one is dropped.
+            (_, SaveEvent::ChangeSaved) => Self::Saved(None),
+            (Self::Saved(_), SaveEvent::IndicatorTimedOut) => Self::Idle,
+            (status, _) => status,
+        }
+    }
+}
+
+struct Deleted {
+    todo: Todo,
+    // Dropped with the slot, so an older timeout can't clear a newer deletion.
+    timeout: Option<CmdHandle>,
+}
+
+// Keeps a `beforeunload` handler registered, dropping the guard removes it.
+// The handler has to run synchronously, a seed stream would only see the event afterwards.
+struct UnloadGuard(Closure<dyn Fn(web_sys::Event) -> JsValue>);
+
+impl UnloadGuard {
+    fn install() -> Self {
+        let handler = Closure::wrap(Box::new(|event: web_sys::Event| {
+            event.prevent_default();
+            // Browsers show their own text, it only has to be set.
+            let message = JsValue::from_str("The edited todo hasn't been saved");
+            if let Err(error) =
+                js_sys::Reflect::set(&event, &JsValue::from_str("returnValue"), &message)
+            {
+                error!("Could not ask to stay on the page", error);
+            }
+            message
+        }) as Box<dyn Fn(web_sys::Event) -> JsValue>);
+        window().set_onbeforeunload(Some(handler.as_ref().unchecked_ref()));
+        Self(handler)
+    }
+}
+
+impl Drop for UnloadGuard {
+    fn drop(&mut self) {
+        window().set_onbeforeunload(None);
+    }
+}
+
+// A transient message, dismissed automatically when its timeout fires.
+struct Notice {
+    text: String,
+    // The timeout is aborted when a newer notice replaces this one.
+    _timeout: CmdHandle,
+}
+
+#[derive(Copy, Clone, Deserialize, Serialize)]
+enum ActivityKind {
+    Create,
+    Complete,
+    Rename,
+    Delete,
+}
 
 #[derive(Deserialize, Serialize)]
-struct Todo {
-    id: Ulid,
-    title: String,
-    completed: bool,
+struct ActivityEntry {
+    timestamp: i64,
+    kind: ActivityKind,
+    todo_id: TodoId,
+    // The title, or "old → new" for renames.
+    detail: String,
 }
 
-struct SelectedTodo {
-    id: Ulid,
-    title: String,
-    input_element: ElRef<web_sys::HtmlInputElement>,
+// The entries describing how `previous` turned into `current`.
+fn activity_between(
+    previous: &BTreeMap<TodoId, Todo>,
+    current: &BTreeMap<TodoId, Todo>,
+    timestamp: i64,
+) -> Vec<ActivityEntry> {
+    let entry = |kind, todo: &Todo, detail: String| ActivityEntry {
+        timestamp,
+        kind,
+        todo_id: todo.id,
+        detail,
+    };
+    let deleted = previous
+        .values()
+        .filter(|todo| not(current.contains_key(&todo.id)))
+        .map(|todo| entry(ActivityKind::Delete, todo, todo.title.clone()));
+    let changed = current.values().flat_map(|todo| {
+        let previous = previous.get(&todo.id);
+        let created = previous
+            .is_none()
+            .then(|| entry(ActivityKind::Create, todo, todo.title.clone()));
+        let renamed = previous
+            .filter(|previous| previous.title != todo.title)
+            .map(|previous| {
+                entry(
+                    ActivityKind::Rename,
+                    todo,
+                    format!("{} → {}", previous.title, todo.title),
+                )
+            });
+        let completed = (todo.completed
+            && previous.map_or(false, |previous| not(previous.completed)))
+        .then(|| entry(ActivityKind::Complete, todo, todo.title.clone()));
+        created.into_iter().chain(renamed).chain(completed)
+    });
+    deleted.chain(changed).collect()
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, EnumIter)]
-enum Filter {
-    All,
-    Active,
-    Completed,
+struct Highlight {
+    id: TodoId,
+    _timeout: CmdHandle,
 }
 
-impl From<Url> for Filter {
-    fn from(mut url: Url) -> Self {
-        match url.remaining_hash_path_parts().as_slice() {
-            [ACTIVE] => Self::Active,
-            [COMPLETED] => Self::Completed,
-            _ => Self::All,
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum PomodoroPhase {
+    Focus,
+    Break,
+}
+
+struct DistractionShield {
+    started_at: i64,
+    // Re-renders the elapsed time every second, dropping it stops the ticks.
+    ticker: Option<StreamHandle>,
+}
+
+impl DistractionShield {
+    fn new(started_at: i64) -> Self {
+        Self {
+            started_at,
+            ticker: None,
         }
     }
 }
 
-// ------ ------
-//    Update
-// ------ ------
+// `fullscreenchange` also fires when the browser leaves full screen by itself (Escape, F11, ...),
+// then the shield and its timer end too. Returns whether the shield ended.
+fn sync_distraction_shield(shield: &mut Option<DistractionShield>, fullscreen: bool) -> bool {
+    if fullscreen || shield.is_none() {
+        return false;
+    }
+    *shield = None;
+    true
+}
 
-// `Msg` describes the different events you can modify state with.
-enum Msg {
-    NewTodoTitleChanged(String),
-    UrlChanged(subs::UrlChanged),
-    // Basic todo operations
-    CreateTodo,
-    ToggleTodo(Ulid),
-    RemoveTodo(Ulid),
-    // Bulk todo operations
-    CheckOrUncheckAll,
-    ClearCompleted,
-    // select operations
-    SelectTodo(Option<Ulid>),
-    SelectedTodoTitleChanged(String),
-    SaveSelectedTodo,
+// A focus session on one todo, followed by a break.
+struct Pomodoro {
+    todo_id: TodoId,
+    phase: PomodoroPhase,
+    remaining_secs: u32,
+    // `Some` while running, dropping it stops the ticks.
+    ticker: Option<StreamHandle>,
 }
 
-// `update` describes how to handle each `Msg`.
-fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
-    match msg {
-        Msg::NewTodoTitleChanged(title) => {
-            model.new_todo_title = title;
-        }
-        Msg::UrlChanged(subs::UrlChanged(url)) => {
-            model.filter = Filter::from(url);
-        }
-        Msg::CreateTodo => {
-            let title = model.new_todo_title.trim();
-            if !title.is_empty() {
-                let id = Ulid::new();
-                model.todos.insert(
-                    id,
-                    Todo {
-                        id,
-                        title: title.to_owned(),
-                        completed: false,
-                    },
-                );
-                model.new_todo_title.clear();
-            }
-        }
-        Msg::ToggleTodo(id) => {
-            if let Some(todo) = model.todos.get_mut(&id) {
-                todo.completed = not(todo.completed);
-            }
+impl Pomodoro {
+    fn new(todo_id: TodoId) -> Self {
+        Self {
+            todo_id,
+            phase: PomodoroPhase::Focus,
+            remaining_secs: POMODORO_FOCUS_SECS,
+            ticker: None,
         }
-        Msg::RemoveTodo(id) => {
-            model.todos.remove(&id);
+    }
+
+    // Count down a second. Once the phase runs out, switch to the other one
+    // and return the phase that just ended.
+    fn tick(&mut self) -> Option<PomodoroPhase> {
+        self.remaining_secs = self.remaining_secs.saturating_sub(1);
+        if self.remaining_secs > 0 {
+            return None;
         }
-        Msg::CheckOrUncheckAll => {
-            let all_checked = model.todos.values().all(|todo| todo.completed);
-            for todo in model.todos.values_mut() {
-                todo.completed = !all_checked;
+        let ended = self.phase;
+        let (phase, remaining_secs) = match ended {
+            PomodoroPhase::Focus => (PomodoroPhase::Break, POMODORO_BREAK_SECS),
+            PomodoroPhase::Break => (PomodoroPhase::Focus, POMODORO_FOCUS_SECS),
+        };
+        self.phase = phase;
+        self.remaining_secs = remaining_secs;
+        Some(ended)
+    }
+}
+
+// Keeps Tab / Shift+Tab inside a modal and gives the focus back to the trigger on close.
+// A modal renders itself through `view_modal` and holds one of these while open.
+struct FocusTrap {
+    container: ElRef<web_sys::HtmlElement>,
+    // Focused before the modal opened, usually the button that opened it.
+    trigger: Option<web_sys::HtmlElement>,
+}
+
+impl FocusTrap {
+    fn open(orders: &mut impl Orders<Msg>) -> Self {
+        let container = ElRef::new();
+        orders.after_next_render({
+            let container = container.clone();
+            move |_| {
+                if let Some(first) = focusable_elements(&container).first() {
+                    focus(first);
+                }
             }
+        });
+        Self {
+            container,
+            trigger: document()
+                .active_element()
+                .and_then(|element| element.dyn_into().ok()),
         }
-        Msg::ClearCompleted => {
-            model.todos = mem::take(&mut model.todos)
-                .into_iter()
-                .filter(|(_, todo)| !todo.completed)
-                .collect();
+    }
+
+    fn close(self) {
+        if let Some(trigger) = &self.trigger {
+            focus(trigger);
         }
-        Msg::SelectTodo(Some(id)) => {
-            if let Some(todo) = model.todos.get(&id) {
-                let input_element = ElRef::new();
+    }
+}
 
-                model.selected_todo = Some(SelectedTodo {
-                    id,
-                    title: todo.title.clone(),
-                    input_element: input_element.clone(),
-                });
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Settings {
+    // Used when the app is opened without a filter in the URL.
+    default_filter: Filter,
+    // The todos are `PUT` to and fetched (`GET`) from this endpoint when set.
+    sync_url: Option<String>,
+    // When set, "a, b, c" + Enter creates one todo per segment.
+    split_delimiter: Option<char>,
+    // Completed todos disappear from the All view this long after being completed.
+    auto_hide_completed_secs: Option<u32>,
+    // The kind of ids new todos get, e.g. UUIDs for backends expecting them.
+    id_kind: IdKind,
+    // "buy milk" is saved as "Buy milk", off by default.
+    auto_capitalize: bool,
+    // Points planned per day, 0 disables the capacity check.
+    daily_capacity: u8,
+    // All lists the completed todos as a single "N completed — show" row.
+    summarize_completed: bool,
+    // The list is an `ol`, numbering the todos in the order they're shown.
+    numbered_list: bool,
+    // The list shows the todos with the most points first, see `list_order`.
+    sort_by_points: bool,
+    // Checking the last checklist item completes the todo.
+    complete_with_checklist: bool,
+    // The browser asks before closing the tab while the edited title isn't saved.
+    confirm_unsaved_edit: bool,
+    // Escape saves the edited todo instead of cancelling the edit like in TodoMVC.
+    esc_saves: bool,
+    // "Clear completed" adds a browser history entry, going back restores the cleared todos.
+    history_undo: bool,
+    // The header briefly shows "Saved" after each change, or "Save failed".
+    show_save_status: bool,
+    // At most this many todos can be active, completing one makes room for the next.
+    wip_limit: Option<usize>,
+    // Completing this many todos in a day is celebrated with a banner.
+    daily_goal: Option<u32>,
+    // Key combos (see `key_combo`) bound to actions, empty means `default_keymap`.
+    keymap: BTreeMap<String, Action>,
+}
 
-                let title_length = u32::try_from(todo.title.len()).expect("title length as u32");
-                orders.after_next_render(move |_| {
-                    let input_element = input_element.get().expect("input element");
+// What a global shortcut can do.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumIter, Deserialize, Serialize)]
+pub enum Action {
+    CycleFilter,
+    ToggleCompletedVisibility,
+    ToggleAll,
+    ClearCompleted,
+    Undo,
+    Redo,
+    Print,
+    ToggleSettings,
+    ToggleMinimized,
+}
 
-                    input_element
-                        .set_selection_range(title_length, title_length)
-                        .expect("move curse to end of input_element");
-                });
-            }
+impl Action {
+    fn title(self) -> &'static str {
+        match self {
+            Self::CycleFilter => "Next filter",
+            Self::ToggleCompletedVisibility => "Hide or show completed",
+            Self::ToggleAll => "Mark all as complete",
+            Self::ClearCompleted => "Clear completed",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::Print => "Print",
+            Self::ToggleSettings => "Settings",
+            Self::ToggleMinimized => "Minimize",
         }
-        Msg::SelectTodo(None) => {
-            model.selected_todo = None;
+    }
+
+    fn msg(self) -> Msg {
+        match self {
+            Self::CycleFilter => Msg::CycleFilter,
+            Self::ToggleCompletedVisibility => Msg::ToggleCompletedVisibility,
+            Self::ToggleAll => Msg::CheckOrUncheckAll,
+            Self::ClearCompleted => Msg::ClearCompleted,
+            Self::Undo => Msg::Undo,
+            Self::Redo => Msg::Redo,
+            Self::Print => Msg::Print,
+            Self::ToggleSettings => Msg::ToggleSettings,
+            Self::ToggleMinimized => Msg::ToggleMinimized,
         }
-        Msg::SelectedTodoTitleChanged(title) => {
-            if let Some(selected_todo) = &mut model.selected_todo {
-                selected_todo.title = title;
-            }
+    }
+}
+
+// Two-key shortcuts like `g` then `n`, matched by `KeySequence` before the keymap.
+#[derive(Copy, Clone, Debug, PartialEq, EnumIter)]
+enum Sequence {
+    FocusNewTodo,
+    FocusFirstTodo,
+}
+
+impl Sequence {
+    fn title(self) -> &'static str {
+        match self {
+            Self::FocusNewTodo => "Go to the new todo input",
+            Self::FocusFirstTodo => "Go to the first todo",
         }
-        Msg::SaveSelectedTodo => {
-            if let Some(selected_todo) = model.selected_todo.take() {
-                let title = selected_todo.title.trim();
-                if title.is_empty() {
-                    model.todos.remove(&selected_todo.id);
-                } else {
-                    if let Some(todo) = model.todos.get_mut(&selected_todo.id) {
-                        todo.title = title.to_owned();
-                    }
-                }
-            }
+    }
+
+    fn keys(self) -> (&'static str, &'static str) {
+        match self {
+            Self::FocusNewTodo => ("g", "n"),
+            Self::FocusFirstTodo => ("g", "t"),
         }
     }
 
-    LocalStorage::insert(STORAGE_KEY, &model.todos).expect("Save todos into local storage");
-}
+    fn msg(self) -> Msg {
+        match self {
+            Self::FocusNewTodo => Msg::FocusNewTodo,
+            Self::FocusFirstTodo => Msg::FocusFirstTodo,
+        }
+    }
 
-// ------ ------
-//     View
-// ------ ------
+    fn starting_with(combo: &str) -> Option<Self> {
+        Self::iter().find(|sequence| sequence.keys().0 == combo)
+    }
+}
 
-// `view` describes what to display.
-fn view(model: &Model) -> Vec<Node<Msg>> {
-    nodes![
-        view_header(&model.new_todo_title),
-        IF!(not(model.todos.is_empty()) => vec![
-            view_main(&model.todos, model.selected_todo.as_ref(), model.filter),
-            view_footer(&model.todos, model.filter),
-        ]),
-    ]
+enum SequenceMatch {
+    Complete(Sequence),
+    // The key starts a sequence, the next one decides.
+    Partial,
+    NoMatch,
 }
 
-fn view_header(new_todo_title: &str) -> Node<Msg> {
-    header![
-        C!["header"],
-        h1!["todos"],
-        input![
-            C!["new-todo"],
-            attrs! {At::Placeholder => "What needs to be done?", At::AutoFocus => AtValue::None, At::Value => new_todo_title},
-            input_ev(Ev::Input, Msg::NewTodoTitleChanged),
-            keyboard_ev(Ev::KeyDown, |keyboard_event| {
-                IF!(keyboard_event.key() == ENTER_KEY => Msg::CreateTodo)
-            })
-        ]
-    ]
+// Remembers the first key of a sequence until the second one or the timeout.
+#[derive(Default)]
+struct KeySequence {
+    // The first key and when it was pressed (ms).
+    pending: Option<(String, i64)>,
 }
 
-fn view_main(
-    todos: &BTreeMap<Ulid, Todo>,
-    selected_todo: Option<&SelectedTodo>,
-    filter: Filter,
-) -> Node<Msg> {
-    section![
-        C!["main"],
-        view_toggle_all(todos),
-        view_todo_list(todos, selected_todo, filter),
-    ]
+impl KeySequence {
+    fn press(&mut self, combo: &str, now: i64) -> SequenceMatch {
+        let pending = self
+            .pending
+            .take()
+            .filter(|(_, pressed_at)| now - pressed_at <= KEY_SEQUENCE_TIMEOUT_MS);
+        if let Some((first, _)) = pending {
+            if let Some(sequence) =
+                Sequence::iter().find(|sequence| sequence.keys() == (first.as_str(), combo))
+            {
+                return SequenceMatch::Complete(sequence);
+            }
+        }
+        if Sequence::starting_with(combo).is_some() {
+            self.pending = Some((combo.to_owned(), now));
+            return SequenceMatch::Partial;
+        }
+        SequenceMatch::NoMatch
+    }
 }
 
-fn view_toggle_all(todos: &BTreeMap<Ulid, Todo>) -> Vec<Node<Msg>> {
-    let all_completed = todos.values().all(|todo| todo.completed);
+// Ctrl+Shift+Enter instead of Ctrl+A to keep the browser's "select all".
+// Undo and redo are bound to both Ctrl (most platforms) and Meta (Cmd on macOS).
+fn default_keymap() -> BTreeMap<String, Action> {
     vec![
-        input![
-            C!["toggle-all"],
-            attrs! {At::Id => "toggle-all", At::Type => "checkbox", At::Checked => all_completed.as_at_value()},
-            ev(Ev::Change, |_| Msg::CheckOrUncheckAll)
-        ],
-        label![attrs! {At::For => "toggle-all"}, "Mark all as complete"],
+        ("f", Action::CycleFilter),
+        ("c", Action::ToggleCompletedVisibility),
+        ("Ctrl+Shift+Enter", Action::ToggleAll),
+        ("Ctrl+z", Action::Undo),
+        ("Meta+z", Action::Undo),
+        ("Ctrl+Shift+z", Action::Redo),
+        ("Meta+Shift+z", Action::Redo),
     ]
+    .into_iter()
+    .map(|(combo, action)| (combo.to_owned(), action))
+    .collect()
 }
 
-fn view_todo_list(
-    todos: &BTreeMap<Ulid, Todo>,
-    selected_todo: Option<&SelectedTodo>,
-    filter: Filter,
-) -> Node<Msg> {
-    let todos = todos.values().filter(|todo| match filter {
-        Filter::All => true,
-        Filter::Active => !todo.completed,
-        Filter::Completed => todo.completed,
-    });
+// A customized keymap replaces the defaults as a whole.
+fn lookup_action(keymap: &BTreeMap<String, Action>, combo: &str) -> Option<Action> {
+    if keymap.is_empty() {
+        default_keymap().get(combo).copied()
+    } else {
+        keymap.get(combo).copied()
+    }
+}
 
-    ul![
-        C!["todo-list"],
-        todos.map(|todo| {
-            let id = todo.id;
-            let is_selected = Some(id) == selected_todo.map(|selected_todo| selected_todo.id);
-            li![
-                C![
-                    IF!(todo.completed => "completed"),
-                    IF!(is_selected => "editing")
-                ],
-                el_key(&todo.id),
-                div![
-                    C!["view"],
-                    input![
-                        C!["toggle"],
-                        attrs! {At::Type => "checkbox", At::Checked => todo.completed.as_at_value()},
-                        ev(Ev::Change, move |_| Msg::ToggleTodo(id))
-                    ],
-                    label![
-                        &todo.title,
-                        ev(Ev::DblClick, move |_| Msg::SelectTodo(Some(id)))
-                    ],
-                    button![C!["destroy"], ev(Ev::Click, move |_| Msg::RemoveTodo(id))],
-                ],
-                IF!(is_selected => {
-                    let selected_todo = selected_todo.unwrap();
-                    input![
-                        C!["edit"],
-                        el_ref(&selected_todo.input_element),
-                        attrs! {At::Value => selected_todo.title},
-                        keyboard_ev(Ev::KeyDown, |keyboard_event| {
-                            IF!(keyboard_event.key() == ESC_KEY => Msg::SelectTodo(None))
-                        }),
-                        input_ev(Ev::Input, Msg::SelectedTodoTitleChanged),
-                        keyboard_ev(Ev::KeyDown, |keyboard_event| {
-                            match keyboard_event.key().as_str() {
-                                ESC_KEY => Some(Msg::SelectTodo(None)),
-                                ENTER_KEY => Some(Msg::SaveSelectedTodo),
-                                _ => return None,
-                            }
-                        }),
-                        ev(Ev::Blur, |_| Msg::SaveSelectedTodo),
-                    ]
-                })
-            ]
-        })
-    ]
+#[derive(Clone, PartialEq)]
+struct Rebinding {
+    action: Action,
+    // The combo being changed, `None` adds another one (e.g. Meta+z next to Ctrl+z).
+    replaced: Option<String>,
 }
 
-// ------ footer ------
+// What an already used combo is bound to.
+#[derive(Debug, PartialEq)]
+enum Clash {
+    Action(Action),
+    // The combo starts the sequence, so a binding of it would never fire.
+    Sequence(Sequence),
+}
 
-fn view_footer(todos: &BTreeMap<Ulid, Todo>, selected_filter: Filter) -> Node<Msg> {
-    let completed_count = todos.values().filter(|todo| todo.completed).count();
-    let active_count = todos.len() - completed_count;
+impl Clash {
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Action(action) => action.title(),
+            Self::Sequence(sequence) => sequence.title(),
+        }
+    }
+}
 
-    footer![
-        C!["footer"],
-        // This should be `0 items left` by default
-        span![
-            C!["todo-count"],
-            strong![completed_count],
-            format!(" item{} left", if active_count == 1 { "" } else { "s" }),
-        ],
-        view_filters(selected_filter),
-        IF!(completed_count > 0 => button![C!["clear-completed"], "Clear completed", ev(Ev::Click, |_| Msg::ClearCompleted)])
-    ]
+// Binds `combo` in place of `rebinding.replaced`, the action's other combos stay.
+// A combo used by another action or a sequence is refused, `Err` names it.
+fn rebind(
+    keymap: &BTreeMap<String, Action>,
+    rebinding: &Rebinding,
+    combo: String,
+) -> Result<BTreeMap<String, Action>, Clash> {
+    if let Some(sequence) = Sequence::starting_with(&combo) {
+        return Err(Clash::Sequence(sequence));
+    }
+    match lookup_action(keymap, &combo) {
+        Some(bound) if bound != rebinding.action => return Err(Clash::Action(bound)),
+        _ => (),
+    }
+    let mut keymap = if keymap.is_empty() {
+        default_keymap()
+    } else {
+        keymap.clone()
+    };
+    if let Some(replaced) = &rebinding.replaced {
+        keymap.remove(replaced);
+    }
+    keymap.insert(combo, rebinding.action);
+    Ok(keymap)
 }
 
-fn view_filters(selected_filter: Filter) -> Node<Msg> {
-    ul![
-        C!["filters"],
-        Filter::iter().map(|filter| {
-            let (link, title) = match filter {
-                Filter::All => ("", "All"),
-                Filter::Active => (ACTIVE, "Active"),
-                Filter::Completed => (COMPLETED, "Completed"),
-            };
-            li![a![
-                C![IF!(filter == selected_filter => "selected")],
-                attrs! { At::Href => format!("#/{}", link) },
-                title
-            ]]
-        })
-    ]
+// E.g. "f", "Ctrl+Shift+Enter" or "Meta+z". Letters are lower-cased, Shift is spelled out.
+// Returns `None` for a lone modifier key.
+fn key_combo(keyboard_event: &web_sys::KeyboardEvent) -> Option<String> {
+    let key = keyboard_event.key();
+    if matches!(key.as_str(), "Control" | "Alt" | "Shift" | "Meta") {
+        return None;
+    }
+    let mut combo = String::new();
+    for (pressed, modifier) in &[
+        (keyboard_event.ctrl_key(), "Ctrl+"),
+        (keyboard_event.alt_key(), "Alt+"),
+        (keyboard_event.shift_key(), "Shift+"),
+        (keyboard_event.meta_key(), "Meta+"),
+    ] {
+        if *pressed {
+            combo.push_str(modifier);
+        }
+    }
+    if key.chars().count() == 1 {
+        combo.push_str(&key.to_lowercase());
+    } else {
+        combo.push_str(&key);
+    }
+    Some(combo)
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum SyncStatus {
+    Idle,
+    Syncing,
+    Error,
+}
+
+// The body sent to and expected from the sync endpoint.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct SyncPayload<T> {
+    version: u64,
+    todos: T,
+}
+
+pub type RemoteTodos = SyncPayload<BTreeMap<TodoId, Todo>>;
+
+enum Merge {
+    // Both sides changed, the todos have to be merged field by field.
+    Combine(RemoteTodos),
+    // The server has newer todos.
+    TakeRemote(RemoteTodos),
+    // The local todos are newer, the server should get them.
+    PushLocal,
+    UpToDate,
+}
+
+// Last write wins: whichever side has the higher version keeps its todos, unless both sides
+// changed since the last sync. Two clients bumping the same version leave equal versions with
+// different todos, so an equal version only means up to date when the todos match too.
+fn merge_remote(
+    local_version: u64,
+    local_todos: &BTreeMap<TodoId, Todo>,
+    base: Option<&RemoteTodos>,
+    remote: RemoteTodos,
+) -> Merge {
+    let both_changed = base.map_or(false, |base| {
+        remote.version > base.version && not(same_todos(&base.todos, local_todos))
+    });
+    if both_changed {
+        return Merge::Combine(remote);
+    }
+    match remote.version.cmp(&local_version) {
+        std::cmp::Ordering::Greater => Merge::TakeRemote(remote),
+        std::cmp::Ordering::Less => Merge::PushLocal,
+        std::cmp::Ordering::Equal if &remote.todos == local_todos => Merge::UpToDate,
+        std::cmp::Ordering::Equal => Merge::Combine(remote),
+    }
+}
+
+// The parts of a todo the three-way merge compares separately.
+#[derive(Copy, Clone, EnumIter)]
+enum TodoField {
+    Title,
+    Completed,
+    Backlog,
+    BlockedBy,
+    Icon,
+    Points,
+    Comments,
+    Checklist,
+    Progress,
+    Start,
+    Audio,
+}
+
+impl TodoField {
+    fn title(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Completed => "completion",
+            Self::Backlog => "backlog",
+            Self::BlockedBy => "dependencies",
+            Self::Icon => "icon",
+            Self::Points => "points",
+            Self::Comments => "comments",
+            Self::Checklist => "checklist",
+            Self::Progress => "progress",
+            Self::Start => "start date",
+            Self::Audio => "voice note",
+        }
+    }
+
+    fn same(self, a: &Todo, b: &Todo) -> bool {
+        match self {
+            Self::Title => a.title == b.title,
+            Self::Completed => a.completed == b.completed,
+            Self::Backlog => a.backlog == b.backlog,
+            Self::BlockedBy => a.blocked_by == b.blocked_by,
+            Self::Icon => a.icon == b.icon,
+            Self::Points => a.points == b.points,
+            Self::Comments => a.comments == b.comments,
+            Self::Checklist => a.checklist == b.checklist,
+            Self::Progress => a.progress == b.progress,
+            Self::Start => a.start == b.start,
+            // The sync base only has fingerprints.
+            Self::Audio => {
+                let fingerprint = |todo: &Todo| todo.audio.as_deref().map(audio_fingerprint);
+                fingerprint(a) == fingerprint(b)
+            }
+        }
+    }
+
+    fn copy(self, from: &Todo, to: &mut Todo) {
+        match self {
+            Self::Title => to.title = from.title.clone(),
+            Self::Completed => to.completed = from.completed,
+            Self::Backlog => to.backlog = from.backlog,
+            Self::BlockedBy => to.blocked_by = from.blocked_by.clone(),
+            Self::Icon => to.icon = from.icon.clone(),
+            Self::Points => to.points = from.points,
+            Self::Comments => to.comments = from.comments.clone(),
+            Self::Checklist => to.checklist = from.checklist.clone(),
+            Self::Progress => to.progress = from.progress,
+            Self::Start => to.start = from.start,
+            Self::Audio => to.audio = from.audio.clone(),
+        }
+    }
+}
+
+// Like `==`, but voice notes are compared by their fingerprints.
+fn same_todo(a: &Todo, b: &Todo) -> bool {
+    a.id == b.id && TodoField::iter().all(|field| field.same(a, b))
+}
+
+fn same_todos(a: &BTreeMap<TodoId, Todo>, b: &BTreeMap<TodoId, Todo>) -> bool {
+    a.len() == b.len() && a.values().zip(b.values()).all(|(a, b)| same_todo(a, b))
+}
+
+// A voice note as stored in the sync base, e.g. "#9f2c3e1a0b4d5c6e-31245". The base is only
+// compared against, so a second copy of every recording in `localStorage` isn't needed.
+// Fingerprints are returned as they are.
+fn audio_fingerprint(audio: &str) -> String {
+    if audio.starts_with('#') {
+        return audio.to_owned();
+    }
+    // FNV-1a
+    let hash = audio.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("#{:016x}-{}", hash, audio.len())
+}
+
+fn fingerprint_audio(todos: &BTreeMap<TodoId, Todo>) -> BTreeMap<TodoId, Todo> {
+    let mut todos = todos.clone();
+    for todo in todos.values_mut() {
+        todo.audio = todo
+            .audio
+            .as_deref()
+            .map(|audio| audio_fingerprint(audio).into());
+    }
+    todos
+}
+
+struct Conflict {
+    id: TodoId,
+    kind: ConflictKind,
+}
+
+enum ConflictKind {
+    // Changed differently on both sides, the merged todo has the local values.
+    Fields(Vec<TodoField>, Box<Todo>),
+    // Deleted on one side and changed on the other, the changed todo is kept.
+    DeletedRemotely,
+    DeletedLocally,
+}
+
+#[derive(Copy, Clone)]
+pub enum Resolution {
+    // Leave the merged todo as it is.
+    Keep,
+    // Take the other side's values, or its deletion.
+    Override,
+}
+
+// Three-way merge of todos both sides changed since `base`, the todos as of the last sync.
+// A field changed on one side takes that change, a field changed differently on both sides
+// keeps the local value and is reported as a conflict.
+fn merge_todos(
+    base: &BTreeMap<TodoId, Todo>,
+    local: &BTreeMap<TodoId, Todo>,
+    remote: &BTreeMap<TodoId, Todo>,
+) -> (BTreeMap<TodoId, Todo>, Vec<Conflict>) {
+    let ids: BTreeSet<TodoId> = base
+        .keys()
+        .chain(local.keys())
+        .chain(remote.keys())
+        .copied()
+        .collect();
+    let mut merged = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    for id in ids {
+        let todo = match (base.get(&id), local.get(&id), remote.get(&id)) {
+            (base, Some(local), Some(remote)) => {
+                let (todo, fields) = merge_fields(base, local, remote);
+                if not(fields.is_empty()) {
+                    let kind = ConflictKind::Fields(fields, Box::new(remote.clone()));
+                    conflicts.push(Conflict { id, kind });
+                }
+                Some(todo)
+            }
+            // Added on one side only.
+            (None, Some(todo), None) | (None, None, Some(todo)) => Some(todo.clone()),
+            (Some(base), Some(local), None) if same_todo(local, base) => None,
+            (Some(_), Some(local), None) => {
+                let kind = ConflictKind::DeletedRemotely;
+                conflicts.push(Conflict { id, kind });
+                Some(local.clone())
+            }
+            (Some(base), None, Some(remote)) if same_todo(remote, base) => None,
+            (Some(_), None, Some(remote)) => {
+                let kind = ConflictKind::DeletedLocally;
+                conflicts.push(Conflict { id, kind });
+                Some(remote.clone())
+            }
+            (_, None, None) => None,
+        };
+        if let Some(todo) = todo {
+            merged.insert(id, todo);
+        }
+    }
+    (merged, conflicts)
+}
+
+// Without a `base` (added on both sides) every difference is a conflict.
+fn merge_fields(base: Option<&Todo>, local: &Todo, remote: &Todo) -> (Todo, Vec<TodoField>) {
+    let mut merged = local.clone();
+    let mut conflicts = Vec::new();
+    for field in TodoField::iter() {
+        let changed = |todo: &Todo| base.map_or(true, |base| not(field.same(base, todo)));
+        if field.same(local, remote) || not(changed(remote)) {
+            continue;
+        }
+        if changed(local) {
+            conflicts.push(field);
+        } else {
+            field.copy(remote, &mut merged);
+        }
+    }
+    (merged, conflicts)
+}
+
+impl Model {}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Todo {
+    id: TodoId,
+    title: String,
+    completed: bool,
+    // "Someday/maybe" todos live outside the All/Active/Completed views.
+    #[serde(default)]
+    backlog: bool,
+    // The todo can't be completed until all of these are completed.
+    #[serde(default)]
+    blocked_by: Vec<TodoId>,
+    // An emoji shown in front of the title.
+    #[serde(default)]
+    icon: Option<String>,
+    // Effort estimate, todos without one don't count towards the capacity.
+    #[serde(default)]
+    points: Option<u8>,
+    // `(timestamp_ms, text)` in the order they were added.
+    #[serde(default)]
+    comments: Vec<(i64, String)>,
+    // Acceptance criteria as `(text, checked)`.
+    #[serde(default)]
+    checklist: Vec<(String, bool)>,
+    // Percent done (0–100) of an active todo, see `Todo::progress`.
+    #[serde(default)]
+    progress: u8,
+    // The todo is planned to begin on this day, until then it's only shown in `Upcoming`.
+    #[serde(default)]
+    start: Option<Day>,
+    // A short recording as a `data:` URL, see `VOICE_NOTE_MAX_BYTES`.
+    // Shared, so the undo snapshots (clones of the todos) don't copy it. It's left out of the
+    // history entries and only a fingerprint goes into the sync base, see `fingerprint_audio`.
+    #[serde(default)]
+    audio: Option<Rc<str>>,
+}
+
+impl Todo {
+    fn new(id: TodoId, title: String) -> Self {
+        Self {
+            id,
+            title,
+            completed: false,
+            backlog: false,
+            blocked_by: Vec::new(),
+            icon: None,
+            points: None,
+            comments: Vec::new(),
+            checklist: Vec::new(),
+            progress: 0,
+            start: None,
+            audio: None,
+        }
+    }
+
+    // Reopened at 100% it starts over, otherwise it keeps its progress.
+    fn toggle_completed(&mut self) {
+        self.completed = not(self.completed);
+        if not(self.completed) && self.progress == 100 {
+            self.progress = 0;
+        }
+    }
+
+    // 100% completes the todo and less reopens it. Returns whether the completion changed.
+    fn set_progress(&mut self, progress: u8) -> bool {
+        self.progress = progress.min(100);
+        let completed = self.progress == 100;
+        let changed = completed != self.completed;
+        self.completed = completed;
+        changed
+    }
+
+    // Completed todos are done, whatever was stored (e.g. todos completed before progress existed).
+    fn progress(&self) -> u8 {
+        if self.completed {
+            100
+        } else {
+            self.progress
+        }
+    }
+
+    // `(checked, total)` checklist items.
+    fn checklist_progress(&self) -> (usize, usize) {
+        let checked = self
+            .checklist
+            .iter()
+            .filter(|(_, checked)| *checked)
+            .count();
+        (checked, self.checklist.len())
+    }
+
+    // The start date hasn't come yet.
+    fn upcoming(&self) -> bool {
+        self.start.is_some() && self.upcoming_on(Day::today())
+    }
+
+    fn upcoming_on(&self, today: Day) -> bool {
+        self.start.map_or(false, |start| start > today)
+    }
+
+    // `now` is the timestamp (ms) the comment gets. Blank comments are ignored.
+    fn add_comment(&mut self, text: &str, now: i64) {
+        let text = text.trim();
+        if not(text.is_empty()) {
+            self.comments.push((now, text.to_owned()));
+        }
+    }
+
+    fn comments_newest_first(&self) -> impl Iterator<Item = &(i64, String)> {
+        self.comments.iter().rev()
+    }
+
+    // Removed dependencies don't block anymore.
+    fn is_blocked(&self, todos: &BTreeMap<TodoId, Todo>) -> bool {
+        self.blocked_by
+            .iter()
+            .filter_map(|id| todos.get(id))
+            .any(|dependency| not(dependency.completed))
+    }
+}
+
+// Blocked todos can't be completed, so "Mark all as complete" is done without them.
+fn all_completed(todos: &BTreeMap<TodoId, Todo>) -> bool {
+    todos
+        .values()
+        .filter(|todo| Filter::All.matches(todo))
+        .all(|todo| todo.completed || todo.is_blocked(todos))
+}
+
+// Completes the todos in All that aren't blocked. Completing a dependency unblocks its
+// dependents, so it repeats until nothing more can be completed.
+fn complete_all(todos: &mut BTreeMap<TodoId, Todo>) {
+    loop {
+        let ready: Vec<TodoId> = todos
+            .values()
+            .filter(|todo| Filter::All.matches(todo))
+            .filter(|todo| not(todo.completed || todo.is_blocked(todos)))
+            .map(|todo| todo.id)
+            .collect();
+        if ready.is_empty() {
+            return;
+        }
+        for id in ready {
+            if let Some(todo) = todos.get_mut(&id) {
+                todo.completed = true;
+            }
+        }
+    }
+}
+
+// Regular todos showing off a few features.
+fn sample_todos(id_kind: IdKind) -> Vec<Todo> {
+    let mut ids = IdGenerator::new(id_kind);
+    let mut sample = |title: &str| Todo::new(ids.generate(), title.to_owned());
+
+    let edit = sample("Double-click a todo to edit it");
+    let mut completed = sample("Click the circle to complete a todo");
+    completed.completed = true;
+    let mut blocked = sample("While editing, pick the todos that block this one");
+    blocked.blocked_by.push(edit.id);
+    let shortcut = sample("Press f to cycle through the filters");
+    let mut backlog = sample("Someday: hover a todo to move it to the backlog");
+    backlog.backlog = true;
+
+    vec![edit, completed, blocked, shortcut, backlog]
+}
+
+// The points still to do, completed (and backlog) todos and todos without points don't count.
+fn total_points(todos: &BTreeMap<TodoId, Todo>) -> u32 {
+    todos
+        .values()
+        .filter(|todo| Filter::Active.matches(todo))
+        .filter_map(|todo| todo.points)
+        .map(u32::from)
+        .sum()
+}
+
+// Would making `todo_id` depend on `dependency_id` close a loop?
+fn would_create_cycle(
+    todos: &BTreeMap<TodoId, Todo>,
+    todo_id: TodoId,
+    dependency_id: TodoId,
+) -> bool {
+    let mut stack = vec![dependency_id];
+    let mut visited = BTreeSet::new();
+    while let Some(id) = stack.pop() {
+        if id == todo_id {
+            return true;
+        }
+        if visited.insert(id) {
+            if let Some(todo) = todos.get(&id) {
+                stack.extend(&todo.blocked_by);
+            }
+        }
+    }
+    false
+}
+
+// Existing data only has ULIDs, they keep deserializing as `TodoId::Ulid`.
+// Ids of both kinds can live side by side, the setting only picks the kind of new ids.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TodoId {
+    Ulid(Ulid),
+    Uuid(Uuid),
+}
+
+impl TodoId {
+    fn new(kind: IdKind) -> Self {
+        match kind {
+            IdKind::Ulid => Self::Ulid(Ulid::new()),
+            IdKind::Uuid => Self::Uuid(Uuid::new_v4()),
+        }
+    }
+
+    fn parse(id: &str) -> Option<Self> {
+        Ulid::from_string(id)
+            .map(Self::Ulid)
+            .or_else(|_| Uuid::parse_str(id).map(Self::Uuid))
+            .ok()
+    }
+}
+
+impl std::fmt::Display for TodoId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Ulid(id) => id.fmt(f),
+            Self::Uuid(id) => id.fmt(f),
+        }
+    }
+}
+
+// Todos are listed in id order. ULIDs sort by creation time,
+// UUIDs carry no time, so todos with UUIDs end up in an arbitrary (but stable) order.
+#[derive(Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+enum IdKind {
+    Ulid,
+    Uuid,
+}
+
+impl Default for IdKind {
+    fn default() -> Self {
+        Self::Ulid
+    }
+}
+
+// Hands out ids for todos created together, monotonic ULIDs keep them in creation order.
+struct IdGenerator {
+    kind: IdKind,
+    ulids: ulid::Generator,
+}
+
+impl IdGenerator {
+    fn new(kind: IdKind) -> Self {
+        Self {
+            kind,
+            ulids: ulid::Generator::new(),
+        }
+    }
+
+    fn generate(&mut self) -> TodoId {
+        match self.kind {
+            IdKind::Ulid => self
+                .ulids
+                .generate()
+                .map_or_else(|_| TodoId::new(IdKind::Ulid), TodoId::Ulid),
+            IdKind::Uuid => TodoId::new(IdKind::Uuid),
+        }
+    }
+}
+
+// Checks every entry on its own, so one broken todo doesn't fail the whole import.
+// Returns the valid todos and the reasons the others were skipped. Understands:
+// - The todos as this app stores them, `{id: todo}`. Entries have to be complete todos.
+// - Other TodoMVC implementations' `[{title, completed, id}]` arrays (also with `text`/`done`),
+//   and the same array wrapped in `{"todos": [...]}`. Only `title` is required, ids that aren't
+//   ULIDs or UUIDs (usually numbers) are replaced.
+fn validate_import(value: &serde_json::Value, id_kind: IdKind) -> (Vec<Todo>, Vec<String>) {
+    use serde_json::Value;
+
+    let mut todos = Vec::new();
+    let mut skipped = Vec::new();
+    let legacy_todos = match value {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("todos") {
+            Some(Value::Array(entries)) => entries,
+            _ => {
+                for (id, entry) in object {
+                    match serde_json::from_value::<Todo>(entry.clone()) {
+                        Ok(todo) if todo.id.to_string() != *id => {
+                            skipped.push(format!("todo {} is stored under another id", id));
+                        }
+                        Ok(todo) if todo.title.trim().is_empty() => {
+                            skipped.push(format!("todo {} has no title", id));
+                        }
+                        Ok(todo) => todos.push(todo),
+                        Err(error) => skipped.push(format!("todo {}: {}", id, error)),
+                    }
+                }
+                return (todos, skipped);
+            }
+        },
+        _ => return (todos, vec!["not a list of todos".to_owned()]),
+    };
+
+    let mut ids = IdGenerator::new(id_kind);
+    for (number, entry) in (1..).zip(legacy_todos) {
+        let field = |names: &[&str]| names.iter().find_map(|name| entry.get(name));
+        if not(entry.is_object()) {
+            skipped.push(format!("entry {} is not a todo", number));
+            continue;
+        }
+        let title = match field(&["title", "text"]) {
+            Some(Value::String(title)) if not(title.trim().is_empty()) => title.trim(),
+            Some(Value::String(_)) | None => {
+                skipped.push(format!("entry {} has no title", number));
+                continue;
+            }
+            Some(_) => {
+                skipped.push(format!("entry {} has a title that isn't text", number));
+                continue;
+            }
+        };
+        let completed = match field(&["completed", "done"]) {
+            Some(Value::Bool(completed)) => *completed,
+            None | Some(Value::Null) => false,
+            Some(_) => {
+                skipped.push(format!("entry {} isn't completed or not", number));
+                continue;
+            }
+        };
+        let id = entry
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(TodoId::parse)
+            .unwrap_or_else(|| ids.generate());
+        let mut todo = Todo::new(id, title.to_owned());
+        todo.completed = completed;
+        todos.push(todo);
+    }
+    (todos, skipped)
+}
+
+// E.g. ", skipped 2 (entry 1 has no title; entry 3 is not a todo)", empty without any.
+fn skipped_summary(skipped: &[String]) -> String {
+    const LISTED: usize = 3;
+    if skipped.is_empty() {
+        return String::new();
+    }
+    let mut reasons = skipped[..skipped.len().min(LISTED)].join("; ");
+    if skipped.len() > LISTED {
+        reasons.push_str("; …");
+    }
+    format!(", skipped {} ({})", skipped.len(), reasons)
+}
+
+#[derive(Copy, Clone)]
+enum PastedFormat {
+    // The todos as this app stores them.
+    Export,
+    // Other TodoMVC apps' todos, see `validate_import`.
+    Legacy,
+    // One title per line.
+    Lines,
+}
+
+impl PastedFormat {
+    fn title(self) -> &'static str {
+        match self {
+            Self::Export => "exported todos",
+            Self::Legacy => "TodoMVC todos",
+            Self::Lines => "plain text",
+        }
+    }
+}
+
+// Pasted JSON is validated as exported todos or another TodoMVC app's todos,
+// anything else is read line by line. Also returns why entries were skipped.
+fn parse_pasted(text: &str, id_kind: IdKind) -> (PastedFormat, Vec<Todo>, Vec<String>) {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(text) {
+        let format = match &value {
+            serde_json::Value::Object(object) if not(object.contains_key("todos")) => {
+                Some(PastedFormat::Export)
+            }
+            serde_json::Value::Object(_) | serde_json::Value::Array(_) => {
+                Some(PastedFormat::Legacy)
+            }
+            // E.g. a pasted number is a title.
+            _ => None,
+        };
+        if let Some(format) = format {
+            let (todos, skipped) = validate_import(&value, id_kind);
+            return (format, todos, skipped);
+        }
+    }
+    let mut ids = IdGenerator::new(id_kind);
+    let todos = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| not(line.is_empty()))
+        .map(|title| Todo::new(ids.generate(), title.to_owned()))
+        .collect();
+    (PastedFormat::Lines, todos, Vec::new())
+}
+
+#[derive(Copy, Clone)]
+pub enum ExportScope {
+    All,
+    // What the list shows under the current filter, see `visible_todos`.
+    Visible,
+}
+
+// The list columns (`blocked_by`, `comments` and `checklist`) hold their items as JSON,
+// e.g. `[[1618000000000,"Asked Bob"]]`, and stay empty without any. `start` is an ISO date.
+// Voice notes are left out, a `data:` URL doesn't belong into a spreadsheet.
+const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "title",
+    "completed",
+    "backlog",
+    "points",
+    "icon",
+    "blocked_by",
+    "comments",
+    "checklist",
+    "progress",
+    "start",
+];
+
+// One row per todo under a `CSV_COLUMNS` header, quoted where needed (RFC 4180).
+fn to_csv(todos: &[&Todo]) -> String {
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for todo in todos {
+        let fields = [
+            todo.id.to_string(),
+            todo.title.clone(),
+            todo.completed.to_string(),
+            todo.backlog.to_string(),
+            todo.points
+                .map(|points| points.to_string())
+                .unwrap_or_default(),
+            todo.icon.clone().unwrap_or_default(),
+            csv_list(&todo.blocked_by),
+            csv_list(&todo.comments),
+            csv_list(&todo.checklist),
+            todo.progress.to_string(),
+            todo.start.map(Day::to_iso_date).unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+fn csv_list<T: Serialize>(items: &[T]) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    serde_json::to_string(items).unwrap_or_default()
+}
+
+fn parse_csv_list<T: serde::de::DeserializeOwned>(
+    field: &str,
+    column: &str,
+    row: usize,
+) -> Result<Vec<T>, String> {
+    if field.is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(field).map_err(|_| format!("Invalid {} in row {}", column, row))
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+// The columns are found by the header, only `title` is required.
+// Rows without an id (or with one that isn't a ULID or UUID) get a new one.
+fn from_csv(csv: &str, id_kind: IdKind) -> Result<Vec<Todo>, String> {
+    let mut rows = parse_csv(csv)?.into_iter();
+    let header = rows.next().ok_or("The CSV is empty")?;
+    let column = |name: &str| header.iter().position(|column| column.trim() == name);
+    let title_column = column("title").ok_or("The CSV has no title column")?;
+    let (id_column, completed_column, backlog_column, points_column, icon_column) = (
+        column("id"),
+        column("completed"),
+        column("backlog"),
+        column("points"),
+        column("icon"),
+    );
+    let (blocked_by_column, comments_column, checklist_column) = (
+        column("blocked_by"),
+        column("comments"),
+        column("checklist"),
+    );
+    let (progress_column, start_column) = (column("progress"), column("start"));
+
+    let mut ids = IdGenerator::new(id_kind);
+    let mut todos = Vec::new();
+    // The header is row 1.
+    for (row_number, row) in (2..).zip(rows) {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| row.get(column))
+                .map_or("", |field| field.trim())
+        };
+        let title = field(Some(title_column));
+        if title.is_empty() {
+            continue;
+        }
+        let id = TodoId::parse(field(id_column)).unwrap_or_else(|| ids.generate());
+        let mut todo = Todo::new(id, title.to_owned());
+        todo.completed = field(completed_column) == "true";
+        todo.backlog = field(backlog_column) == "true";
+        todo.points = field(points_column).parse().ok();
+        todo.icon = Some(field(icon_column))
+            .filter(|icon| not(icon.is_empty()))
+            .map(str::to_owned);
+        todo.blocked_by = parse_csv_list(field(blocked_by_column), "blocked_by", row_number)?;
+        todo.comments = parse_csv_list(field(comments_column), "comments", row_number)?;
+        todo.checklist = parse_csv_list(field(checklist_column), "checklist", row_number)?;
+        todo.progress = field(progress_column)
+            .parse::<u8>()
+            .unwrap_or_default()
+            .min(100);
+        todo.start = Some(field(start_column))
+            .filter(|start| not(start.is_empty()))
+            .and_then(Day::from_iso_date);
+        todos.push(todo);
+    }
+    Ok(todos)
+}
+
+// Rows of fields, quoted fields may contain commas, doubled quotes and line breaks.
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut characters = csv.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if field.is_empty() => loop {
+                match characters.next() {
+                    Some('"') if characters.peek() == Some(&'"') => {
+                        characters.next();
+                        field.push('"');
+                    }
+                    Some('"') => match characters.peek() {
+                        None | Some(',') | Some('\r') | Some('\n') => break,
+                        Some(_) => {
+                            return Err(format!(
+                                "Unexpected text after the quoted field in row {}",
+                                rows.len() + 1
+                            ))
+                        }
+                    },
+                    Some(character) => field.push(character),
+                    None => {
+                        return Err(format!("Unclosed quote in row {}", rows.len() + 1));
+                    }
+                }
+            },
+            ',' => row.push(mem::take(&mut field)),
+            '\r' if characters.peek() == Some(&'\n') => (),
+            '\r' | '\n' => {
+                row.push(mem::take(&mut field));
+                rows.push(mem::take(&mut row));
+            }
+            character => field.push(character),
+        }
+    }
+    // The last line doesn't need a line break.
+    if not(field.is_empty() && row.is_empty()) {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+// Lower-cased words of at least 3 characters, so "a" or "to" don't make todos related.
+fn title_words(title: &str) -> BTreeSet<String> {
+    title
+        .split(|character: char| not(character.is_alphanumeric()))
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Up to `n` other todos sharing the most title words with `target`, best first.
+fn related_todos(target: &Todo, all: &BTreeMap<TodoId, Todo>, n: usize) -> Vec<TodoId> {
+    let words = title_words(&target.title);
+    let mut scored: Vec<(usize, TodoId)> = all
+        .values()
+        .filter(|other| other.id != target.id)
+        .map(|other| {
+            let shared = title_words(&other.title).intersection(&words).count();
+            (shared, other.id)
+        })
+        .filter(|(shared, _)| *shared > 0)
+        .collect();
+    // Ties keep the list order.
+    scored.sort_by(|(a, a_id), (b, b_id)| b.cmp(a).then(a_id.cmp(b_id)));
+    scored.into_iter().take(n).map(|(_, id)| id).collect()
+}
+
+// A local calendar day, counted in days since 1970-01-01.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+pub struct Day(i64);
+
+impl Day {
+    fn today() -> Self {
+        Self::from_date(&js_sys::Date::new_0())
+    }
+
+    fn from_date(date: &js_sys::Date) -> Self {
+        let local_ms = date.get_time() - date.get_timezone_offset() * MS_PER_MINUTE;
+        Self((local_ms / MS_PER_DAY).floor() as i64)
+    }
+
+    // A `YYYY-MM-DD` value of a date input. It's parsed as UTC midnight,
+    // whose day number is the same as the one of the local calendar day.
+    fn from_iso_date(value: &str) -> Option<Self> {
+        let time = js_sys::Date::new(&JsValue::from_str(value)).get_time();
+        IF!(not(time.is_nan()) => Self((time / MS_PER_DAY).floor() as i64))
+    }
+
+    fn to_iso_date(self) -> String {
+        let date = js_sys::Date::new(&JsValue::from_f64(self.0 as f64 * MS_PER_DAY));
+        String::from(date.to_iso_string())
+            .chars()
+            .take(10)
+            .collect()
+    }
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct CompletedToday {
+    day: Day,
+    count: u32,
+    // The daily goal has been reached (and celebrated) today.
+    #[serde(default)]
+    goal_met: bool,
+}
+
+impl CompletedToday {
+    // Start counting from zero once the stored day isn't today anymore.
+    fn roll_over(&mut self, today: Day) -> bool {
+        if self.day == today {
+            return false;
+        }
+        *self = Self {
+            day: today,
+            count: 0,
+            goal_met: false,
+        };
+        true
+    }
+
+    // `true` only for the completion that reaches the goal, once a day.
+    fn reach_goal(&mut self, goal: Option<u32>) -> bool {
+        let reached = goal.map_or(false, |goal| self.count >= goal);
+        if reached && not(self.goal_met) {
+            self.goal_met = true;
+            return true;
+        }
+        false
+    }
+}
+
+// Consecutive days with at least one completed todo.
+#[derive(Default, Deserialize, Serialize)]
+struct Streak {
+    last_completion: Option<Day>,
+    days: u32,
+}
+
+impl Streak {
+    fn record_completion(&mut self, today: Day) {
+        self.days = update_streak(self.last_completion, today, self.days);
+        self.last_completion = Some(today);
+    }
+
+    // A streak is still alive until a whole day passes without completions.
+    fn current(&self, today: Day) -> u32 {
+        match self.last_completion {
+            Some(Day(last)) if today.0 - last <= 1 => self.days,
+            _ => 0,
+        }
+    }
+}
+
+// Completing again on the same day keeps the streak, the day after extends it,
+// any later day (or a clock moved backwards) starts over.
+fn update_streak(last: Option<Day>, today: Day, current: u32) -> u32 {
+    match last.map(|Day(last)| today.0 - last) {
+        Some(0) => current.max(1),
+        Some(1) => current + 1,
+        _ => 1,
+    }
+}
+
+struct SelectedTodo {
+    id: TodoId,
+    title: String,
+    input_element: ElRef<web_sys::HtmlInputElement>,
+    // A voice note being recorded, ending the edit stops it.
+    recording: Option<Recording>,
+}
+
+// `MediaRecorder` and `MediaStream` aren't among the web-sys features seed enables,
+// so they're driven through `Reflect`. Dropping the recording stops the recorder
+// (which still delivers what it has got) and releases the microphone.
+struct Recording {
+    recorder: JsValue,
+    stream: JsValue,
+    // Stops the recording at `VOICE_NOTE_MAX_SECS`.
+    _timeout: CmdHandle,
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        let state = js_sys::Reflect::get(&self.recorder, &JsValue::from_str("state"))
+            .ok()
+            .and_then(|state| state.as_string());
+        // Stopping an inactive recorder throws.
+        if state.as_deref() != Some("inactive") {
+            if let Err(error) = call_method(&self.recorder, "stop") {
+                error!("Could not stop the recording", error);
+            }
+        }
+        stop_tracks(&self.stream);
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumIter, Deserialize, Serialize)]
+pub enum Filter {
+    All,
+    Active,
+    Upcoming,
+    Completed,
+    Backlog,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl Filter {
+    // The hash path segment (`#/<link>`) and the label of the filter.
+    fn link_and_title(self) -> (&'static str, &'static str) {
+        match self {
+            Self::All => ("", "All"),
+            Self::Active => (ACTIVE, "Active"),
+            Self::Upcoming => (UPCOMING, "Upcoming"),
+            Self::Completed => (COMPLETED, "Completed"),
+            Self::Backlog => (BACKLOG, "Backlog"),
+        }
+    }
+
+    // The current URL with its hash pointing to this filter under the route base.
+    fn to_url(self, base_url: &Url) -> Url {
+        let link = hash_link(base_url, &[self.link_and_title().0]);
+        Url::current().set_hash(link.trim_start_matches('#'))
+    }
+
+    fn matches(self, todo: &Todo) -> bool {
+        self.matches_on(todo, Day::today)
+    }
+
+    // `today` is only asked for todos with a start date.
+    fn matches_on(self, todo: &Todo, today: impl FnOnce() -> Day) -> bool {
+        let upcoming = || todo.start.is_some() && todo.upcoming_on(today());
+        match self {
+            Self::All => !todo.backlog,
+            Self::Active => !todo.backlog && !todo.completed && !upcoming(),
+            Self::Upcoming => !todo.backlog && !todo.completed && upcoming(),
+            Self::Completed => !todo.backlog && todo.completed,
+            Self::Backlog => todo.backlog,
+        }
+    }
+
+    // New todos inherit the context of the view they're created in:
+    // - `Backlog` puts them into the backlog.
+    // - `Completed` leaves them active, completion isn't inheritable.
+    // - `Upcoming` leaves them without a start date, there is no day to inherit.
+    // - `All` and `Active` create plain active todos.
+    fn apply_to_new(self, todo: &mut Todo) {
+        todo.backlog = self == Self::Backlog;
+    }
+}
+
+// Expects the route base to be skipped already (see `Url::skip_hash_base_path`).
+impl From<Url> for Filter {
+    fn from(mut url: Url) -> Self {
+        parse_filter(&url.remaining_hash_path_parts())
+    }
+}
+
+fn todo_link_target(url: &Url, base_url: &Url) -> Option<TodoId> {
+    let mut url = url.clone().skip_hash_base_path(base_url.hash_path());
+    parse_todo_link(&url.remaining_hash_path_parts())
+}
+
+// `#/todo/<id>` (a trailing slash is fine) yields the id, anything else `None`.
+fn parse_todo_link(parts: &[&str]) -> Option<TodoId> {
+    match parts {
+        [link, id] | [link, id, ""] if link.eq_ignore_ascii_case(TODO_LINK) => TodoId::parse(id),
+        _ => None,
+    }
+}
+
+// The filters the `f` shortcut steps through, the others are picked by their links.
+const CYCLED_FILTERS: [Filter; 3] = [Filter::All, Filter::Active, Filter::Completed];
+
+// All → Active → Completed → All. Filters outside the cycle continue at All.
+fn next_filter(filter: Filter) -> Filter {
+    CYCLED_FILTERS
+        .iter()
+        .position(|other| *other == filter)
+        .map_or(Filter::All, |index| {
+            CYCLED_FILTERS[(index + 1) % CYCLED_FILTERS.len()]
+        })
+}
+
+// Hides the completed todos by switching to `Active`, and switches back to the filter
+// that was shown before. Returns the new current and previous filter.
+fn toggle_completed_visibility(current: Filter, previous: Filter) -> (Filter, Filter) {
+    match current {
+        // After toggling back and then picking `Active` by hand, there is nothing to return to.
+        Filter::Active if previous == Filter::Active => (Filter::All, Filter::Active),
+        Filter::Active => (previous, Filter::Active),
+        _ => (Filter::Active, current),
+    }
+}
+
+// Shared links like `#/active/` or `#/Active` should still select the right filter,
+// so empty trailing segments are ignored and the match is case-insensitive.
+// Unknown values fall back to `All`.
+fn parse_filter(parts: &[&str]) -> Filter {
+    let len = parts
+        .iter()
+        .rposition(|part| !part.is_empty())
+        .map_or(0, |index| index + 1);
+
+    match &parts[..len] {
+        [part] if part.eq_ignore_ascii_case(ACTIVE) => Filter::Active,
+        [part] if part.eq_ignore_ascii_case(UPCOMING) => Filter::Upcoming,
+        [part] if part.eq_ignore_ascii_case(COMPLETED) => Filter::Completed,
+        [part] if part.eq_ignore_ascii_case(BACKLOG) => Filter::Backlog,
+        _ => Filter::All,
+    }
+}
+
+// ------ ------
+//    Update
+// ------ ------
+
+// `Msg` describes the different events you can modify state with.
+pub enum Msg {
+    NewTodoTitleChanged(String),
+    UrlChanged(subs::UrlChanged),
+    HighlightTodo(TodoId),
+    ClearHighlight,
+    CopyTodoLink(TodoId),
+    TodoLinkCopied(Result<(), JsValue>, String),
+    CycleFilter,
+    ToggleCompletedVisibility,
+    TypeAhead(char),
+    // Basic todo operations
+    CreateTodo,
+    ScheduleAtChanged(String),
+    ScheduleNewTodo,
+    CancelScheduled(TodoId),
+    ActivateScheduledNow(TodoId),
+    SnoozeTodo(TodoId, i64),
+    ActivateScheduled,
+    AddSamples,
+    ToggleTodo(TodoId),
+    HideCompleted(TodoId),
+    LogDone(String),
+    RemoveTodo(TodoId),
+    ToggleDependency(TodoId, TodoId),
+    SetTodoIcon(TodoId, Option<String>),
+    SetTodoPoints(TodoId, Option<u8>),
+    SetProgress(TodoId, u8),
+    SetStartDate(TodoId, Option<Day>),
+    AddComment(TodoId, String),
+    AddChecklistItem(TodoId, String),
+    ToggleChecklistItem(TodoId, usize),
+    RemoveChecklistItem(TodoId, usize),
+    StartVoiceNote(TodoId),
+    MicrophoneOpened(TodoId, Result<JsValue, JsValue>),
+    StopVoiceNote,
+    VoiceNoteRecorded(TodoId, web_sys::Blob),
+    VoiceNoteRead(TodoId, Result<String, JsValue>),
+    RemoveVoiceNote(TodoId),
+    // Bulk todo operations
+    CheckOrUncheckAll,
+    ClearCompleted,
+    // The state of the history entry the user went back or forward to.
+    RestoreHistoryState(Option<String>),
+    Undo,
+    Redo,
+    // Backlog operations
+    ToggleBacklog(TodoId),
+    BacklogPullCountChanged(String),
+    PullFromBacklog,
+    // select operations
+    SelectTodo(Option<TodoId>),
+    SelectedTodoTitleChanged(String),
+    SaveSelectedTodo,
+    EscapeSelectedTodo,
+    SplitSelection,
+    ToggleMinimized,
+    // pomodoro
+    StartPomodoro(TodoId),
+    SetFocusTask(Option<TodoId>),
+    EnterDistractionShield,
+    FullscreenEntered(Result<(), JsValue>),
+    ExitDistractionShield,
+    FullscreenChanged,
+    ShieldTick,
+    TogglePomodoro,
+    ResetPomodoro,
+    PomodoroTick,
+    // print view
+    Print,
+    PrintFinished,
+    // settings
+    ToggleSettings,
+    DefaultFilterChanged(String),
+    ToggleActivity,
+    MigrateLegacy,
+    PasteImport,
+    ExportCsv(ExportScope),
+    CsvFileChosen(Option<web_sys::File>),
+    CsvRead(Result<String, JsValue>),
+    ClipboardRead(Result<String, JsValue>),
+    SyncUrlChanged(String),
+    SplitDelimiterChanged(String),
+    ToggleAutoCapitalize,
+    ToggleSummarizeCompleted,
+    ToggleNumberedList,
+    ToggleSortByPoints,
+    ToggleCompleteWithChecklist,
+    ToggleConfirmUnsavedEdit,
+    ToggleEscSaves,
+    ToggleHistoryUndo,
+    ToggleShowSaveStatus,
+    HideSaveStatus,
+    ToggleCompletedSummary,
+    DailyCapacityChanged(String),
+    AutoHideCompletedChanged(String),
+    WipLimitChanged(String),
+    DailyGoalChanged(String),
+    DismissCelebration,
+    IdKindChanged(String),
+    StartReset,
+    ResetConfirmationChanged(String),
+    CancelReset,
+    ResetAll,
+    // notices
+    DismissNotice,
+    RestoreDeleted,
+    ForgetDeleted,
+    StorageChanged,
+    // Fired every minute to reset the daily counter after midnight.
+    CheckDayRollover,
+    // keymap
+    Shortcut(String),
+    StartRebind(Action, Option<String>),
+    ResetKeymap,
+    FocusNewTodo,
+    FocusFirstTodo,
+    // sync
+    FetchRemote,
+    RemoteFetched(fetch::Result<RemoteTodos>),
+    PushLocal,
+    LocalPushed(fetch::Result<()>),
+    ResolveConflict(TodoId, Resolution),
+}
+
+// `update` describes how to handle each `Msg`.
+fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
+    match msg {
+        Msg::NewTodoTitleChanged(title) => {
+            model.new_todo_title = title;
+        }
+        Msg::UrlChanged(subs::UrlChanged(url)) => match todo_link_target(&url, &model.base_url) {
+            Some(id) => {
+                orders.send_msg(Msg::HighlightTodo(id));
+            }
+            None => {
+                model.filter = Filter::from(url.skip_hash_base_path(model.base_url.hash_path()))
+            }
+        },
+        Msg::HighlightTodo(id) => {
+            let todo = match model.todos.get(&id) {
+                Some(todo) => todo,
+                None => {
+                    show_notice(model, orders, "Todo not found");
+                    return;
+                }
+            };
+            // Switch to a filter showing the todo, unless the current one does.
+            if not(model.filter.matches(todo)) {
+                model.filter = IF!(todo.backlog => Filter::Backlog).unwrap_or_default();
+            }
+            model.highlighted_todo = Some(Highlight {
+                id,
+                _timeout: orders
+                    .perform_cmd_with_handle(cmds::timeout(HIGHLIGHT_TIMEOUT_MS, || {
+                        Msg::ClearHighlight
+                    })),
+            });
+            orders.after_next_render(move |_| {
+                if let Some(element) = document().get_element_by_id(&todo_element_id(id)) {
+                    element.scroll_into_view();
+                }
+            });
+        }
+        Msg::ClearHighlight => {
+            model.highlighted_todo = None;
+        }
+        Msg::CopyTodoLink(id) => {
+            let link = todo_link(&model.base_url, id);
+            match copy_to_clipboard(&link) {
+                Ok(promise) => {
+                    orders.perform_cmd(async move {
+                        Msg::TodoLinkCopied(JsFuture::from(promise).await.map(drop), link)
+                    });
+                }
+                Err(error) => {
+                    orders.send_msg(Msg::TodoLinkCopied(Err(error), link));
+                }
+            }
+        }
+        Msg::TodoLinkCopied(result, link) => {
+            let text = match result {
+                Ok(()) => "Link copied".to_owned(),
+                // Show the link at least, so it can still be copied by hand.
+                Err(_) => format!("Could not copy the link: {}", link),
+            };
+            show_notice(model, orders, text);
+        }
+        Msg::CycleFilter => {
+            model.filter = next_filter(model.filter);
+            model.filter.to_url(&model.base_url).go_and_push();
+        }
+        Msg::ToggleCompletedVisibility => {
+            let (filter, previous_filter) =
+                toggle_completed_visibility(model.filter, model.previous_filter);
+            model.filter = filter;
+            model.previous_filter = previous_filter;
+            model.filter.to_url(&model.base_url).go_and_push();
+        }
+        Msg::TypeAhead(character) => {
+            let now = js_sys::Date::now() as i64;
+            let (buffer, last_typed) = &mut model.type_ahead;
+            if now - *last_typed > TYPE_AHEAD_RESET_MS {
+                buffer.clear();
+            }
+            *last_typed = now;
+            buffer.extend(character.to_lowercase());
+
+            let visible = list_order(
+                visible_todos(&model.todos, model.filter, &model.hidden_completed).collect(),
+                model.settings.sort_by_points,
+            );
+            let current =
+                focused_todo_id().and_then(|id| visible.iter().position(|todo| todo.id == id));
+            // Pressing the same letter again cycles through the todos starting with it.
+            let mut characters = buffer.chars();
+            let first = characters.next();
+            let (search, from_index) = match current {
+                Some(current) if characters.all(|other| Some(other) == first) => {
+                    (first.map(String::from).unwrap_or_default(), current + 1)
+                }
+                current => (buffer.clone(), current.unwrap_or_default()),
+            };
+            if let Some(index) = find_type_ahead(&visible, &search, from_index) {
+                let id = visible[index].id;
+                if let Some(element) = document()
+                    .get_element_by_id(&todo_element_id(id))
+                    .and_then(|element| element.dyn_into().ok())
+                {
+                    focus(&element);
+                }
+            }
+            orders.skip();
+            return;
+        }
+        Msg::CreateTodo => {
+            let mut ids = IdGenerator::new(model.settings.id_kind);
+            let todos: Vec<Todo> =
+                split_titles(&model.new_todo_title, model.settings.split_delimiter)
+                    .into_iter()
+                    .map(|title| {
+                        let title = IF!(model.settings.auto_capitalize => capitalize_first(&title))
+                            .unwrap_or(title);
+                        let mut todo = Todo::new(ids.generate(), title);
+                        model.filter.apply_to_new(&mut todo);
+                        todo
+                    })
+                    .collect();
+            let new_active = todos
+                .iter()
+                .filter(|todo| Filter::Active.matches(todo))
+                .count();
+            // All of the todos or none, so the kept title can simply be submitted again.
+            if not(fits_wip_limit(
+                &model.todos,
+                model.settings.wip_limit,
+                new_active,
+            )) {
+                show_notice(model, orders, wip_limit_notice(model));
+            } else if not(todos.is_empty()) {
+                for todo in todos {
+                    model.todos.insert(todo.id, todo);
+                }
+                model.new_todo_title.clear();
+                // Keep typing the next todo, whatever the browser did with the focus.
+                let new_todo_input = model.new_todo_input.clone();
+                orders.after_next_render(move |_| {
+                    if let Some(input) = new_todo_input.get() {
+                        focus(&input);
+                    }
+                });
+            }
+        }
+        Msg::ScheduleAtChanged(schedule_at) => {
+            model.schedule_at = schedule_at;
+        }
+        Msg::ScheduleNewTodo => {
+            let title = model.new_todo_title.trim();
+            // `datetime-local` values are parsed as local time.
+            let timestamp = js_sys::Date::new(&JsValue::from_str(&model.schedule_at)).get_time();
+            if title.is_empty() || timestamp.is_nan() {
+                return;
+            }
+            let mut todo = Todo::new(TodoId::new(model.settings.id_kind), title.to_owned());
+            model.filter.apply_to_new(&mut todo);
+            model.scheduled.insert(todo.id, (todo, timestamp as i64));
+            save_scheduled(&model.scheduled);
+            model.new_todo_title.clear();
+            model.schedule_at.clear();
+        }
+        Msg::CancelScheduled(id) => {
+            model.scheduled.remove(&id);
+            save_scheduled(&model.scheduled);
+        }
+        Msg::ActivateScheduledNow(id) => {
+            if let Some((todo, _)) = model.scheduled.get(&id) {
+                let todo = todo.clone();
+                let activated = change_todos(model, orders, |todos| {
+                    todos.insert(id, todo);
+                });
+                if activated {
+                    model.scheduled.remove(&id);
+                    save_scheduled(&model.scheduled);
+                }
+            }
+        }
+        // A snoozed todo is scheduled again, `ActivateScheduled` brings it back.
+        Msg::SnoozeTodo(id, activate_at) => {
+            if let Some(todo) = model.todos.remove(&id) {
+                model.scheduled.insert(id, (todo, activate_at));
+                save_scheduled(&model.scheduled);
+                check_selected_todo_exists(model, orders);
+            }
+        }
+        Msg::ActivateScheduled => {
+            let now = js_sys::Date::now() as i64;
+            let due = activate_due(&mut model.scheduled, now);
+            if due.is_empty() {
+                orders.skip();
+                return;
+            }
+            let activating = due.clone();
+            let activated = change_todos(model, orders, |todos| {
+                todos.extend(activating.into_iter().map(|todo| (todo.id, todo)));
+            });
+            // Refused todos stay due, the next check tries them again.
+            if activated {
+                save_scheduled(&model.scheduled);
+            } else {
+                model
+                    .scheduled
+                    .extend(due.into_iter().map(|todo| (todo.id, (todo, now))));
+            }
+        }
+        Msg::AddSamples => {
+            let samples = sample_todos(model.settings.id_kind);
+            change_todos(model, orders, |todos| {
+                todos.extend(samples.into_iter().map(|todo| (todo.id, todo)));
+            });
+        }
+        Msg::ToggleTodo(id) => {
+            let refusal = toggle_refusal(&model.todos, model.settings.wip_limit, id);
+            // The view explains blocked todos on their checkbox.
+            if let Some(ToggleRefusal::WipLimit) = refusal {
+                show_notice(model, orders, wip_limit_notice(model));
+                return;
+            }
+            if let (None, Some(todo)) = (refusal, model.todos.get_mut(&id)) {
+                todo.toggle_completed();
+                completion_changed(model, orders, id);
+            }
+        }
+        Msg::HideCompleted(id) => {
+            model.pending_hides.remove(&id);
+            if model.todos.get(&id).map_or(false, |todo| todo.completed) {
+                model.hidden_completed.insert(id);
+            }
+        }
+        Msg::LogDone(text) => {
+            if log_done(&mut model.done_log, js_sys::Date::now() as i64, &text) {
+                save_done_log(&model.done_log);
+            }
+        }
+        Msg::RemoveTodo(id) => {
+            // Dropping the handle cancels a pending hide.
+            model.pending_hides.remove(&id);
+            model.hidden_completed.remove(&id);
+            if let Some(deleted) = delete_todo(&mut model.todos, &mut model.last_deleted, id) {
+                deleted.timeout = Some(
+                    orders.perform_cmd_with_handle(cmds::timeout(UNDO_DELETE_TIMEOUT_MS, || {
+                        Msg::ForgetDeleted
+                    })),
+                );
+            }
+        }
+        Msg::RestoreDeleted => {
+            if let Some(deleted) = &model.last_deleted {
+                let todo = deleted.todo.clone();
+                let restored = change_todos(model, orders, |todos| restore_deleted(todos, todo));
+                // A refused restore can still be retried until the undo times out.
+                if restored {
+                    model.last_deleted = None;
+                }
+            }
+        }
+        Msg::ForgetDeleted => {
+            model.last_deleted = None;
+        }
+        Msg::ToggleDependency(id, dependency_id) => {
+            let cycle = would_create_cycle(&model.todos, id, dependency_id);
+            if let Some(todo) = model.todos.get_mut(&id) {
+                if let Some(index) = todo.blocked_by.iter().position(|id| *id == dependency_id) {
+                    todo.blocked_by.remove(index);
+                } else if not(cycle) {
+                    todo.blocked_by.push(dependency_id);
+                }
+            }
+        }
+        Msg::SetTodoIcon(id, icon) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                todo.icon = icon;
+            }
+        }
+        Msg::SetTodoPoints(id, points) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                todo.points = points;
+            }
+        }
+        // 100% completes the todo and less reopens it, with the checkbox's refusals.
+        Msg::SetProgress(id, progress) => {
+            let progress = progress.min(100);
+            let toggles = model
+                .todos
+                .get(&id)
+                .map_or(false, |todo| (progress == 100) != todo.completed);
+            // The progress is kept only together with the completion change it implies.
+            if toggles {
+                match toggle_refusal(&model.todos, model.settings.wip_limit, id) {
+                    Some(ToggleRefusal::Blocked) => {
+                        show_notice(model, orders, "Blocked by unfinished todos");
+                        return;
+                    }
+                    Some(ToggleRefusal::WipLimit) => {
+                        show_notice(model, orders, wip_limit_notice(model));
+                        return;
+                    }
+                    None => {}
+                }
+            }
+            let changed = model
+                .todos
+                .get_mut(&id)
+                .map_or(false, |todo| todo.set_progress(progress));
+            if changed {
+                completion_changed(model, orders, id);
+            }
+        }
+        // Clearing the start date (or moving it to today) makes an upcoming todo active.
+        Msg::SetStartDate(id, start) => {
+            change_todos(model, orders, |todos| {
+                if let Some(todo) = todos.get_mut(&id) {
+                    todo.start = start;
+                }
+            });
+        }
+        Msg::AddComment(id, text) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                todo.add_comment(&text, js_sys::Date::now() as i64);
+            }
+        }
+        Msg::AddChecklistItem(id, text) => {
+            let text = text.trim();
+            if let (false, Some(todo)) = (text.is_empty(), model.todos.get_mut(&id)) {
+                todo.checklist.push((text.to_owned(), false));
+            }
+        }
+        Msg::ToggleChecklistItem(id, index) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                if let Some((_, checked)) = todo.checklist.get_mut(index) {
+                    *checked = not(*checked);
+                }
+                let (checked, total) = todo.checklist_progress();
+                if model.settings.complete_with_checklist && checked == total && not(todo.completed)
+                {
+                    orders.send_msg(Msg::ToggleTodo(id));
+                }
+            }
+        }
+        Msg::RemoveChecklistItem(id, index) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                if index < todo.checklist.len() {
+                    todo.checklist.remove(index);
+                }
+            }
+        }
+        Msg::StartVoiceNote(id) => {
+            orders.perform_cmd(async move { Msg::MicrophoneOpened(id, open_microphone().await) });
+        }
+        Msg::MicrophoneOpened(id, Ok(stream)) => {
+            let selected_todo = match &mut model.selected_todo {
+                Some(selected_todo)
+                    if selected_todo.id == id && selected_todo.recording.is_none() =>
+                {
+                    selected_todo
+                }
+                // The edit ended while the browser asked for the permission.
+                _ => {
+                    stop_tracks(&stream);
+                    return;
+                }
+            };
+            let msg_sender = orders.msg_sender();
+            let on_data = move |blob| msg_sender(Some(Msg::VoiceNoteRecorded(id, blob)));
+            match start_recorder(&stream, on_data) {
+                Ok(recorder) => {
+                    let timeout = orders.perform_cmd_with_handle(cmds::timeout(
+                        VOICE_NOTE_MAX_SECS * 1_000,
+                        || Msg::StopVoiceNote,
+                    ));
+                    selected_todo.recording = Some(Recording {
+                        recorder,
+                        stream,
+                        _timeout: timeout,
+                    });
+                }
+                Err(error) => {
+                    error!("Could not start the recording", error);
+                    stop_tracks(&stream);
+                    show_notice(model, orders, "Could not record a voice note");
+                }
+            }
+        }
+        Msg::MicrophoneOpened(_, Err(error)) => {
+            // Browsers reject with a `NotAllowedError` when the permission is denied.
+            let name = js_sys::Reflect::get(&error, &JsValue::from_str("name"))
+                .ok()
+                .and_then(|name| name.as_string());
+            let text = if name.as_deref() == Some("NotAllowedError") {
+                "Microphone access was denied, allow it in the browser to record voice notes"
+            } else {
+                "Could not access the microphone"
+            };
+            show_notice(model, orders, text);
+        }
+        Msg::StopVoiceNote => {
+            if let Some(selected_todo) = &mut model.selected_todo {
+                selected_todo.recording = None;
+            }
+        }
+        Msg::VoiceNoteRecorded(id, blob) => {
+            if not(fits_voice_note_cap(blob.size() as usize)) {
+                show_notice(model, orders, "The voice note is too large to store");
+                return;
+            }
+            orders.perform_cmd(async move { Msg::VoiceNoteRead(id, read_data_url(blob).await) });
+        }
+        Msg::VoiceNoteRead(id, Ok(data_url)) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                todo.audio = Some(data_url.into());
+            }
+        }
+        Msg::VoiceNoteRead(_, Err(error)) => {
+            error!("Could not read the voice note", error);
+            show_notice(model, orders, "Could not save the voice note");
+        }
+        Msg::RemoveVoiceNote(id) => {
+            if let Some(todo) = model.todos.get_mut(&id) {
+                todo.audio = None;
+            }
+        }
+        Msg::CheckOrUncheckAll => {
+            if all_completed(&model.todos) {
+                let reopened = model
+                    .todos
+                    .values()
+                    .filter(|todo| Filter::Completed.matches(todo) && not(todo.upcoming()))
+                    .count();
+                if not(fits_wip_limit(
+                    &model.todos,
+                    model.settings.wip_limit,
+                    reopened,
+                )) {
+                    show_notice(model, orders, wip_limit_notice(model));
+                    return;
+                }
+                for todo in model.todos.values_mut() {
+                    if Filter::All.matches(todo) {
+                        todo.completed = false;
+                    }
+                }
+            } else {
+                complete_all(&mut model.todos);
+            }
+        }
+        Msg::Undo | Msg::Redo => {
+            let stepped = match msg {
+                Msg::Undo => model.history.undo(&mut model.todos),
+                _ => model.history.redo(&mut model.todos),
+            };
+            if not(stepped) {
+                orders.skip();
+                return;
+            }
+            check_selected_todo_exists(model, orders);
+            save_todos(model);
+            // Undoing isn't a new change, the redo stack has to survive.
+            if let Some(previous) = track_local_change(model, orders) {
+                record_activity(model, &previous);
+                show_saved(model, orders);
+            }
+            return;
+        }
+        Msg::ClearCompleted => {
+            let (cleared, kept): (BTreeMap<_, _>, BTreeMap<_, _>) = mem::take(&mut model.todos)
+                .into_iter()
+                .partition(|(_, todo)| Filter::Completed.matches(todo));
+            model.todos = kept;
+            if model.settings.history_undo && not(cleared.is_empty()) {
+                if let Err(error) = push_history_state(&cleared) {
+                    error!("Could not add the history entry", error);
+                }
+            }
+        }
+        Msg::RestoreHistoryState(state) => {
+            let cleared = match state.as_deref().and_then(parse_history_state) {
+                Some(cleared) => cleared,
+                None => {
+                    orders.skip();
+                    return;
+                }
+            };
+            // Todos changed since then win, and the entry can't restore twice.
+            for (id, todo) in cleared {
+                model.todos.entry(id).or_insert(todo);
+            }
+            if let Err(error) = window()
+                .history()
+                .and_then(|history| history.replace_state(&JsValue::NULL, ""))
+            {
+                error!("Could not clear the history entry", error);
+            }
+        }
+        Msg::ToggleBacklog(id) => {
+            change_todos(model, orders, |todos| {
+                if let Some(todo) = todos.get_mut(&id) {
+                    todo.backlog = not(todo.backlog);
+                }
+            });
+        }
+        Msg::BacklogPullCountChanged(count) => {
+            if let Ok(count) = count.parse() {
+                model.backlog_pull_count = count;
+            }
+        }
+        Msg::PullFromBacklog => {
+            let count = model.backlog_pull_count;
+            change_todos(model, orders, |todos| pull_from_backlog(todos, count));
+        }
+        Msg::SelectTodo(Some(id)) => {
+            // Jumping to a related todo keeps the edit of the current one.
+            if model
+                .selected_todo
+                .as_ref()
+                .map_or(false, |selected_todo| selected_todo.id != id)
+            {
+                save_selected_todo(model);
+            }
+            if let Some(todo) = model.todos.get(&id) {
+                let input_element = ElRef::new();
+
+                model.selected_todo = Some(SelectedTodo {
+                    id,
+                    title: todo.title.clone(),
+                    input_element: input_element.clone(),
+                    recording: None,
+                });
+
+                // Selection offsets are UTF-16 code units.
+                let title_length =
+                    u32::try_from(todo.title.encode_utf16().count()).unwrap_or(u32::MAX);
+                orders.after_next_render(move |_| {
+                    // A misplaced cursor isn't worth crashing for, e.g. the row could be gone already.
+                    let input_element = match input_element.get() {
+                        Some(input_element) => input_element,
+                        None => {
+                            error!("The edit input hasn't been rendered");
+                            return;
+                        }
+                    };
+                    if let Err(error) =
+                        input_element.set_selection_range(title_length, title_length)
+                    {
+                        error!("Could not move the cursor to the end of the input", error);
+                    }
+                });
+            }
+        }
+        Msg::SelectTodo(None) => {
+            model.selected_todo = None;
+        }
+        Msg::SelectedTodoTitleChanged(title) => {
+            if let Some(selected_todo) = &mut model.selected_todo {
+                selected_todo.title = title;
+            }
+        }
+        Msg::EscapeSelectedTodo => {
+            orders.skip().send_msg(escape_msg(model.settings.esc_saves));
+        }
+        Msg::SaveSelectedTodo => {
+            if not(check_selected_todo_exists(model, orders)) {
+                return;
+            }
+            save_selected_todo(model);
+        }
+        Msg::ToggleMinimized => {
+            model.minimized = not(model.minimized);
+            if let Err(error) = LocalStorage::insert(MINIMIZED_STORAGE_KEY, &model.minimized) {
+                error!("Could not save the minimized state", error);
+            }
+        }
+        Msg::SetFocusTask(id) => {
+            model.focus_task = id;
+            save_focus_task(model.focus_task);
+        }
+        // The app's root goes full screen, the view then shows only the focus task.
+        Msg::EnterDistractionShield => {
+            if model.focus_task.is_none() {
+                return;
+            }
+            let promise = document()
+                .get_elements_by_class_name("todoapp")
+                .item(0)
+                .ok_or_else(|| JsValue::from_str("There is no .todoapp element"))
+                .and_then(|root| call_method(&root, "requestFullscreen"))
+                .and_then(|promise| promise.dyn_into::<js_sys::Promise>().map_err(JsValue::from));
+            match promise {
+                Ok(promise) => {
+                    orders.perform_cmd(async move {
+                        Msg::FullscreenEntered(JsFuture::from(promise).await.map(drop))
+                    });
+                }
+                Err(error) => {
+                    orders.send_msg(Msg::FullscreenEntered(Err(error)));
+                }
+            }
+        }
+        Msg::FullscreenEntered(Ok(())) => {
+            let mut shield = DistractionShield::new(js_sys::Date::now() as i64);
+            shield.ticker =
+                Some(orders.stream_with_handle(streams::interval(1_000, || Msg::ShieldTick)));
+            model.distraction_shield = Some(shield);
+        }
+        Msg::FullscreenEntered(Err(error)) => {
+            error!("Could not enter full screen", error);
+            show_notice(model, orders, "Full screen isn't available in this browser");
+        }
+        Msg::ExitDistractionShield => {
+            model.distraction_shield = None;
+            if document().fullscreen_element().is_some() {
+                document().exit_fullscreen();
+            }
+        }
+        Msg::FullscreenChanged => {
+            let fullscreen = document().fullscreen_element().is_some();
+            if not(sync_distraction_shield(
+                &mut model.distraction_shield,
+                fullscreen,
+            )) {
+                orders.skip();
+            }
+            // Only the shield changed, there are no todos to save.
+            return;
+        }
+        // Only the elapsed time changed.
+        Msg::ShieldTick => return,
+        Msg::StartPomodoro(id) => {
+            let mut pomodoro = Pomodoro::new(id);
+            pomodoro.ticker = Some(start_pomodoro_ticker(orders));
+            model.pomodoro = Some(pomodoro);
+        }
+        Msg::TogglePomodoro => {
+            if let Some(pomodoro) = &mut model.pomodoro {
+                pomodoro.ticker = match pomodoro.ticker.take() {
+                    Some(_) => None,
+                    None => Some(start_pomodoro_ticker(orders)),
+                };
+            }
+        }
+        Msg::ResetPomodoro => {
+            model.pomodoro = None;
+        }
+        Msg::PomodoroTick => {
+            let ended = model.pomodoro.as_mut().and_then(Pomodoro::tick);
+            match ended {
+                Some(PomodoroPhase::Focus) => {
+                    show_notice(model, orders, "Focus session done, take a 5 minute break");
+                }
+                Some(PomodoroPhase::Break) => {
+                    // The next focus session waits until the user resumes.
+                    if let Some(pomodoro) = &mut model.pomodoro {
+                        pomodoro.ticker = None;
+                    }
+                    show_notice(model, orders, "Break is over, resume when you're ready");
+                }
+                None => (),
+            }
+            // Nothing but the countdown has changed.
+            return;
+        }
+        Msg::Print => {
+            model.printing = true;
+            // `print` blocks until the dialog is closed, so the app chrome returns right after.
+            orders.after_next_render(|_| {
+                if let Err(error) = window().print() {
+                    error!("Could not open the print dialog", error);
+                }
+                Msg::PrintFinished
+            });
+        }
+        Msg::PrintFinished => {
+            model.printing = false;
+        }
+        Msg::ToggleSettings => {
+            model.settings_open = not(model.settings_open);
+            if model.settings_open {
+                model.focus_trap = Some(FocusTrap::open(orders));
+            } else if let Some(focus_trap) = model.focus_trap.take() {
+                model.rebinding = None;
+                focus_trap.close();
+            }
+        }
+        Msg::ToggleActivity => {
+            model.activity_open = not(model.activity_open);
+        }
+        Msg::DefaultFilterChanged(link) => {
+            model.settings.default_filter = parse_filter(&[&link]);
+            save_settings(&model.settings);
+        }
+        Msg::AutoHideCompletedChanged(secs) => {
+            model.settings.auto_hide_completed_secs = secs.parse().ok().filter(|secs| *secs > 0);
+            save_settings(&model.settings);
+        }
+        Msg::WipLimitChanged(limit) => {
+            model.settings.wip_limit = limit.parse().ok().filter(|limit| *limit > 0);
+            save_settings(&model.settings);
+        }
+        Msg::DailyGoalChanged(goal) => {
+            model.settings.daily_goal = goal.parse().ok().filter(|goal| *goal > 0);
+            save_settings(&model.settings);
+        }
+        Msg::DismissCelebration => {
+            model.celebrating = false;
+        }
+        Msg::IdKindChanged(kind) => {
+            model.settings.id_kind = match kind.as_str() {
+                "uuid" => IdKind::Uuid,
+                _ => IdKind::Ulid,
+            };
+            save_settings(&model.settings);
+        }
+        Msg::DailyCapacityChanged(capacity) => {
+            model.settings.daily_capacity = capacity.parse().unwrap_or_default();
+            save_settings(&model.settings);
+        }
+        Msg::ToggleHistoryUndo => {
+            model.settings.history_undo = not(model.settings.history_undo);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleShowSaveStatus => {
+            model.settings.show_save_status = not(model.settings.show_save_status);
+            save_settings(&model.settings);
+        }
+        Msg::HideSaveStatus => {
+            change_save_status(model, SaveEvent::IndicatorTimedOut);
+        }
+        Msg::ToggleEscSaves => {
+            model.settings.esc_saves = not(model.settings.esc_saves);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleConfirmUnsavedEdit => {
+            model.settings.confirm_unsaved_edit = not(model.settings.confirm_unsaved_edit);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleCompleteWithChecklist => {
+            model.settings.complete_with_checklist = not(model.settings.complete_with_checklist);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleSummarizeCompleted => {
+            model.settings.summarize_completed = not(model.settings.summarize_completed);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleNumberedList => {
+            model.settings.numbered_list = not(model.settings.numbered_list);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleSortByPoints => {
+            model.settings.sort_by_points = not(model.settings.sort_by_points);
+            save_settings(&model.settings);
+        }
+        Msg::ToggleCompletedSummary => {
+            model.completed_summary_expanded = not(model.completed_summary_expanded);
+        }
+        Msg::ToggleAutoCapitalize => {
+            model.settings.auto_capitalize = not(model.settings.auto_capitalize);
+            save_settings(&model.settings);
+        }
+        Msg::SplitDelimiterChanged(delimiter) => {
+            model.settings.split_delimiter = delimiter.trim().chars().next();
+            save_settings(&model.settings);
+        }
+        Msg::SyncUrlChanged(url) => {
+            let url = url.trim();
+            model.settings.sync_url = IF!(not(url.is_empty()) => url.to_owned());
+            save_settings(&model.settings);
+            model.sync_status = SyncStatus::Idle;
+            if model.settings.sync_url.is_some() {
+                orders.send_msg(Msg::FetchRemote);
+            }
+        }
+        Msg::MigrateLegacy => {
+            let storage = window().local_storage().ok().flatten();
+            let mut found = Vec::new();
+            let mut skipped = Vec::new();
+            for key in LEGACY_STORAGE_KEYS {
+                let json = match storage
+                    .as_ref()
+                    .and_then(|storage| storage.get_item(key).ok())
+                {
+                    Some(Some(json)) => json,
+                    _ => continue,
+                };
+                let value = match serde_json::from_str(&json) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        skipped.push(format!("{} isn't JSON", key));
+                        continue;
+                    }
+                };
+                let (todos, key_skipped) = validate_import(&value, model.settings.id_kind);
+                skipped.extend(
+                    key_skipped
+                        .into_iter()
+                        .map(|reason| format!("{}: {}", key, reason)),
+                );
+                found.extend(todos);
+            }
+            let mut imported = 0;
+            if not(change_todos(model, orders, |todos| {
+                imported = add_new_todos(todos, found)
+            })) {
+                return;
+            }
+            let text = match (imported, skipped.is_empty()) {
+                (0, true) => "No todos from other TodoMVC apps found".to_owned(),
+                (0, false) => format!(
+                    "Imported no todos from other TodoMVC apps{}",
+                    skipped_summary(&skipped)
+                ),
+                (1, _) => format!(
+                    "Imported 1 todo from other TodoMVC apps{}",
+                    skipped_summary(&skipped)
+                ),
+                (count, _) => format!(
+                    "Imported {} todos from other TodoMVC apps{}",
+                    count,
+                    skipped_summary(&skipped)
+                ),
+            };
+            show_notice(model, orders, text);
+        }
+        Msg::PasteImport => match read_clipboard() {
+            Ok(promise) => {
+                orders.perform_cmd(async move {
+                    Msg::ClipboardRead(
+                        JsFuture::from(promise)
+                            .await
+                            .map(|text| text.as_string().unwrap_or_default()),
+                    )
+                });
+            }
+            Err(error) => {
+                orders.send_msg(Msg::ClipboardRead(Err(error)));
+            }
+        },
+        Msg::ExportCsv(scope) => {
+            let (file_name, todos): (_, Vec<&Todo>) = match scope {
+                ExportScope::All => ("todos.csv", model.todos.values().collect()),
+                ExportScope::Visible => (
+                    "todos-visible.csv",
+                    list_order(
+                        visible_todos(&model.todos, model.filter, &model.hidden_completed)
+                            .collect(),
+                        model.settings.sort_by_points,
+                    ),
+                ),
+            };
+            if let Err(error) = download(file_name, &to_csv(&todos)) {
+                error!("Could not export the todos", error);
+                show_notice(model, orders, "Could not export the todos");
+            }
+        }
+        Msg::CsvFileChosen(Some(file)) => {
+            orders.perform_cmd(async move {
+                Msg::CsvRead(
+                    JsFuture::from(file.text())
+                        .await
+                        .map(|text| text.as_string().unwrap_or_default()),
+                )
+            });
+        }
+        Msg::CsvFileChosen(None) => (),
+        Msg::CsvRead(Ok(csv)) => {
+            let text = match from_csv(&csv, model.settings.id_kind) {
+                Ok(todos) => {
+                    let found = todos.len();
+                    let mut imported = 0;
+                    if not(change_todos(model, orders, |model_todos| {
+                        imported = add_new_todos(model_todos, todos)
+                    })) {
+                        return;
+                    }
+                    match imported {
+                        imported if imported < found => format!(
+                            "Imported {} of {} todos from the CSV, the others already exist",
+                            imported, found
+                        ),
+                        1 => "Imported 1 todo from the CSV".to_owned(),
+                        imported => format!("Imported {} todos from the CSV", imported),
+                    }
+                }
+                Err(error) => format!("Could not import the CSV: {}", error),
+            };
+            show_notice(model, orders, text);
+        }
+        Msg::CsvRead(Err(error)) => {
+            error!("Could not read the CSV file", error);
+            show_notice(model, orders, "Could not read the CSV file");
+        }
+        Msg::ClipboardRead(Ok(text)) => {
+            let (format, todos, skipped) = parse_pasted(&text, model.settings.id_kind);
+            let found = todos.len();
+            let mut imported = 0;
+            if not(change_todos(model, orders, |model_todos| {
+                imported = add_new_todos(model_todos, todos)
+            })) {
+                return;
+            }
+            let skipped = skipped_summary(&skipped);
+            let text = match (found, imported) {
+                (0, _) if skipped.is_empty() => "Nothing to import in the clipboard".to_owned(),
+                (0, _) => format!("Imported nothing from the clipboard{}", skipped),
+                (found, imported) if imported < found => format!(
+                    "Imported {} of {} todos from pasted {}, the others already exist{}",
+                    imported,
+                    found,
+                    format.title(),
+                    skipped
+                ),
+                (_, 1) => format!("Imported 1 todo from pasted {}{}", format.title(), skipped),
+                (_, imported) => format!(
+                    "Imported {} todos from pasted {}{}",
+                    imported,
+                    format.title(),
+                    skipped
+                ),
+            };
+            show_notice(model, orders, text);
+        }
+        Msg::ClipboardRead(Err(error)) => {
+            // Browsers reject the read with a `NotAllowedError` when the permission is denied.
+            let name = js_sys::Reflect::get(&error, &JsValue::from_str("name"))
+                .ok()
+                .and_then(|name| name.as_string());
+            let text = if name.as_deref() == Some("NotAllowedError") {
+                "Clipboard access was denied, allow it in the browser to paste todos"
+            } else {
+                "Could not read the clipboard"
+            };
+            show_notice(model, orders, text);
+        }
+        Msg::StartReset => {
+            model.reset_confirmation = Some(String::new());
+        }
+        Msg::ResetConfirmationChanged(text) => {
+            model.reset_confirmation = Some(text);
+        }
+        Msg::CancelReset => {
+            model.reset_confirmation = None;
+        }
+        Msg::ResetAll => {
+            if not(model
+                .reset_confirmation
+                .as_deref()
+                .map_or(false, reset_confirmed))
+            {
+                return;
+            }
+            for key in &[
+                STORAGE_KEY,
+                COMPLETED_TODAY_STORAGE_KEY,
+                SETTINGS_STORAGE_KEY,
+                SYNC_VERSION_STORAGE_KEY,
+                SYNC_BASE_STORAGE_KEY,
+                DONE_LOG_STORAGE_KEY,
+                MINIMIZED_STORAGE_KEY,
+                STREAK_STORAGE_KEY,
+                ACTIVITY_STORAGE_KEY,
+                SCHEDULED_STORAGE_KEY,
+                FOCUS_TASK_STORAGE_KEY,
+            ] {
+                if let Err(error) = LocalStorage::remove(key) {
+                    error!("Could not remove", key, error);
+                }
+            }
+            model.todos.clear();
+            model.done_log.clear();
+            model.activity.clear();
+            model.scheduled.clear();
+            model.minimized = false;
+            model.selected_todo = None;
+            model.settings = Settings::default();
+            model.completed_today = CompletedToday::default();
+            model.completed_today.roll_over(Day::today());
+            model.streak = Streak::default();
+            model.sync_version = 0;
+            model.sync_status = SyncStatus::Idle;
+            model.sync_base = None;
+            model.pushing = None;
+            model.conflicts.clear();
+            model.saved_todos = model.todos.clone();
+            model.settings_open = false;
+            if let Some(focus_trap) = model.focus_trap.take() {
+                focus_trap.close();
+            }
+            model.reset_confirmation = None;
+            model.unload_guard = None;
+            model.focus_task = None;
+            model.last_deleted = None;
+            // Undo would bring the deleted todos back and save them again.
+            model.history = History::default();
+            model.hidden_completed.clear();
+            model.pending_hides.clear();
+            model.pomodoro = None;
+            model.celebrating = false;
+            if model.distraction_shield.take().is_some()
+                && document().fullscreen_element().is_some()
+            {
+                document().exit_fullscreen();
+            }
+            // Everything has been removed from the storage, don't write the empty todos back.
+            return;
+        }
+        Msg::DismissNotice => {
+            model.notice = None;
+        }
+        Msg::StorageChanged => {
+            model.todos = LocalStorage::get(STORAGE_KEY).unwrap_or_default();
+            model.sync_version = LocalStorage::get(SYNC_VERSION_STORAGE_KEY).unwrap_or_default();
+            model.saved_todos = model.todos.clone();
+            check_selected_todo_exists(model, orders);
+            // The todos have just been loaded, there is nothing new to save.
+            return;
+        }
+        Msg::Shortcut(combo) => {
+            if let Some(rebinding) = model.rebinding.take() {
+                match combo.as_str() {
+                    // Tab keeps moving the focus through the settings.
+                    TAB_KEY | "Shift+Tab" => {
+                        model.rebinding = Some(rebinding);
+                        orders.skip();
+                    }
+                    // Escape cancels the rebinding.
+                    ESC_KEY => (),
+                    _ => match rebind(&model.settings.keymap, &rebinding, combo.clone()) {
+                        Ok(keymap) => {
+                            model.settings.keymap = keymap;
+                            save_settings(&model.settings);
+                        }
+                        Err(clash) => {
+                            let text =
+                                format!("{} is already used by \"{}\"", combo, clash.title());
+                            show_notice(model, orders, text);
+                        }
+                    },
+                }
+            } else if model.distraction_shield.is_some() && combo == ESC_KEY {
+                orders.send_msg(Msg::ExitDistractionShield);
+            } else {
+                orders.skip();
+                match model.key_sequence.press(&combo, js_sys::Date::now() as i64) {
+                    SequenceMatch::Complete(sequence) => {
+                        orders.send_msg(sequence.msg());
+                    }
+                    SequenceMatch::Partial => (),
+                    SequenceMatch::NoMatch => {
+                        if let Some(action) = lookup_action(&model.settings.keymap, &combo) {
+                            orders.send_msg(action.msg());
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        // Focused after the render, otherwise the key would also be typed into the input.
+        Msg::FocusNewTodo => {
+            let new_todo_input = model.new_todo_input.clone();
+            orders.after_next_render(move |_| {
+                if let Some(input) = new_todo_input.get() {
+                    focus(&input);
+                }
+            });
+        }
+        Msg::FocusFirstTodo => {
+            let first = list_order(
+                visible_todos(&model.todos, model.filter, &model.hidden_completed).collect(),
+                model.settings.sort_by_points,
+            )
+            .first()
+            .map(|todo| todo.id);
+            if let Some(id) = first {
+                orders.after_next_render(move |_| {
+                    if let Some(element) = document()
+                        .get_element_by_id(&todo_element_id(id))
+                        .and_then(|element| element.dyn_into().ok())
+                    {
+                        focus(&element);
+                    }
+                });
+            }
+        }
+        Msg::StartRebind(action, replaced) => {
+            model.rebinding = Some(Rebinding { action, replaced });
+        }
+        Msg::ResetKeymap => {
+            model.settings.keymap.clear();
+            model.rebinding = None;
+            save_settings(&model.settings);
+        }
+        Msg::CheckDayRollover => {
+            if not(model.completed_today.roll_over(Day::today())) {
+                orders.skip();
+                return;
+            }
+            save_completed_today(&model.completed_today);
+            model.celebrating = false;
+            model.focus_task = None;
+            save_focus_task(None);
+        }
+        Msg::SplitSelection => {
+            let selection = model.selected_todo.as_ref().and_then(|selected_todo| {
+                let input_element = selected_todo.input_element.get()?;
+                let start = input_element.selection_start().ok().flatten()?;
+                let end = input_element.selection_end().ok().flatten()?;
+                split_selection(&selected_todo.title, start, end)
+            });
+            if let Some((rest, extracted)) = selection {
+                let todo = Todo::new(TodoId::new(model.settings.id_kind), extracted);
+                let split = change_todos(model, orders, |todos| {
+                    todos.insert(todo.id, todo);
+                });
+                // A refused split keeps the title as it is.
+                if not(split) {
+                    return;
+                }
+                if let Some(selected_todo) = &mut model.selected_todo {
+                    selected_todo.title = rest;
+                }
+            }
+        }
+        Msg::FetchRemote => {
+            if let Some(url) = model.settings.sync_url.clone() {
+                model.sync_status = SyncStatus::Syncing;
+                orders.perform_cmd(async move { Msg::RemoteFetched(fetch_remote(url).await) });
+            }
+        }
+        Msg::RemoteFetched(Ok(remote)) => {
+            let merge = merge_remote(
+                model.sync_version,
+                &model.todos,
+                model.sync_base.as_ref(),
+                remote,
+            );
+            if let Merge::Combine(remote) = merge {
+                // Without a base (never synced, or diverged on the same version) every
+                // difference is a conflict.
+                let base = model.sync_base.take().map(|base| base.todos);
+                let (todos, conflicts) =
+                    merge_todos(&base.unwrap_or_default(), &model.todos, &remote.todos);
+                model.todos = todos;
+                model.conflicts = conflicts;
+                // The merge is a local change, its version ends up above both sides.
+                model.sync_version = model.sync_version.max(remote.version);
+                save_sync_base(&remote);
+                model.sync_base = Some(remote);
+                check_selected_todo_exists(model, orders);
+                model.sync_status = SyncStatus::Idle;
+                // Fall through, the merged todos are saved and pushed like any local change.
+            } else {
+                match merge {
+                    Merge::TakeRemote(remote) => {
+                        model.todos = remote.todos.clone();
+                        model.sync_version = remote.version;
+                        save_sync_version(model.sync_version);
+                        save_sync_base(&remote);
+                        model.sync_base = Some(remote);
+                        check_selected_todo_exists(model, orders);
+                        model.sync_status = SyncStatus::Idle;
+                    }
+                    Merge::PushLocal => {
+                        orders.send_msg(Msg::PushLocal);
+                    }
+                    Merge::UpToDate | Merge::Combine(_) => model.sync_status = SyncStatus::Idle,
+                }
+                // Remote todos must not count as a local change.
+                save_todos(model);
+                model.saved_todos = model.todos.clone();
+                return;
+            }
+        }
+        Msg::RemoteFetched(Err(error)) => {
+            // The local todos stay untouched, they are pushed again with the next change.
+            error!("Could not fetch the remote todos", error);
+            model.sync_status = SyncStatus::Error;
+        }
+        Msg::PushLocal => {
+            if let Some(url) = model.settings.sync_url.clone() {
+                let payload = SyncPayload {
+                    version: model.sync_version,
+                    todos: model.todos.clone(),
+                };
+                match Request::new(url).method(Method::Put).json(&payload) {
+                    Ok(request) => {
+                        model.pushing = Some(payload);
+                        model.sync_status = SyncStatus::Syncing;
+                        orders.perform_cmd(
+                            async move { Msg::LocalPushed(push_local(request).await) },
+                        );
+                    }
+                    Err(error) => {
+                        error!("Could not serialize the todos for syncing", error);
+                        model.sync_status = SyncStatus::Error;
+                    }
+                }
+            }
+        }
+        Msg::LocalPushed(Ok(())) => {
+            if let Some(pushed) = model.pushing.take() {
+                save_sync_base(&pushed);
+                model.sync_base = Some(pushed);
+            }
+            model.sync_status = SyncStatus::Idle;
+        }
+        Msg::LocalPushed(Err(error)) => {
+            error!("Could not push the todos", error);
+            model.pushing = None;
+            model.sync_status = SyncStatus::Error;
+        }
+        Msg::ResolveConflict(id, resolution) => {
+            if let Some(index) = model
+                .conflicts
+                .iter()
+                .position(|conflict| conflict.id == id)
+            {
+                let conflict = model.conflicts.remove(index);
+                if let Resolution::Override = resolution {
+                    match conflict.kind {
+                        ConflictKind::Fields(fields, remote) => {
+                            if let Some(todo) = model.todos.get_mut(&id) {
+                                for field in fields {
+                                    field.copy(&remote, todo);
+                                }
+                            }
+                        }
+                        ConflictKind::DeletedRemotely | ConflictKind::DeletedLocally => {
+                            model.todos.remove(&id);
+                            check_selected_todo_exists(model, orders);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    save_todos(model);
+    if let Some(previous) = track_local_change(model, orders) {
+        record_activity(model, &previous);
+        model.history.record(previous);
+        show_saved(model, orders);
+    }
+    update_unload_guard(model);
+    if model.a11y_audit {
+        orders.after_next_render(|_| audit_accessibility());
+    }
+}
+
+// What Escape in the edit view does.
+fn escape_msg(esc_saves: bool) -> Msg {
+    if esc_saves {
+        Msg::SaveSelectedTodo
+    } else {
+        Msg::SelectTodo(None)
+    }
+}
+
+fn save_selected_todo(model: &mut Model) {
+    if let Some(selected_todo) = model.selected_todo.take() {
+        save_edit(
+            &mut model.todos,
+            &selected_todo,
+            model.settings.auto_capitalize,
+        );
+    }
+}
+
+// An emptied title removes the todo. A todo that no longer exists stays removed.
+fn save_edit(
+    todos: &mut BTreeMap<TodoId, Todo>,
+    selected_todo: &SelectedTodo,
+    auto_capitalize: bool,
+) {
+    let title = selected_todo.title.trim();
+    if title.is_empty() {
+        todos.remove(&selected_todo.id);
+    } else if let Some(todo) = todos.get_mut(&selected_todo.id) {
+        todo.title =
+            IF!(auto_capitalize => capitalize_first(title)).unwrap_or_else(|| title.to_owned());
+    }
+}
+
+fn update_unload_guard(model: &mut Model) {
+    let guarded = model.settings.confirm_unsaved_edit && has_unsaved_edit(model);
+    match (guarded, model.unload_guard.is_some()) {
+        (true, false) => model.unload_guard = Some(UnloadGuard::install()),
+        (false, true) => model.unload_guard = None,
+        _ => (),
+    }
+}
+
+fn has_unsaved_edit(model: &Model) -> bool {
+    model.selected_todo.as_ref().map_or(false, |selected_todo| {
+        model
+            .todos
+            .get(&selected_todo.id)
+            .map_or(false, |todo| todo.title != selected_todo.title.trim())
+    })
+}
+
+// Bump the sync version and push the todos if they differ from the last saved ones.
+// Returns the todos as they were before the change.
+fn track_local_change(
+    model: &mut Model,
+    orders: &mut impl Orders<Msg>,
+) -> Option<BTreeMap<TodoId, Todo>> {
+    if model.todos == model.saved_todos {
+        return None;
+    }
+    let previous = mem::replace(&mut model.saved_todos, model.todos.clone());
+    model.sync_version += 1;
+    save_sync_version(model.sync_version);
+    orders.send_msg(Msg::PushLocal);
+    Some(previous)
+}
+
+fn show_saved(model: &mut Model, orders: &mut impl Orders<Msg>) {
+    if not(model.settings.show_save_status) {
+        return;
+    }
+    change_save_status(model, SaveEvent::ChangeSaved);
+    if let SaveStatus::Saved(timeout @ None) = &mut model.save_status {
+        let hide = cmds::timeout(SAVED_INDICATOR_MS, || Msg::HideSaveStatus);
+        *timeout = Some(orders.perform_cmd_with_handle(hide));
+    }
+}
+
+fn change_save_status(model: &mut Model, event: SaveEvent) {
+    model.save_status = mem::replace(&mut model.save_status, SaveStatus::Idle).next(event);
+}
+
+fn record_activity(model: &mut Model, previous: &BTreeMap<TodoId, Todo>) {
+    let entries = activity_between(previous, &model.todos, js_sys::Date::now() as i64);
+    if entries.is_empty() {
+        return;
+    }
+    model.activity.extend(entries);
+    let overflow = model.activity.len().saturating_sub(ACTIVITY_LIMIT);
+    model.activity.drain(..overflow);
+    if let Err(error) = LocalStorage::insert(ACTIVITY_STORAGE_KEY, &model.activity) {
+        error!("Could not save the activity", error);
+    }
+}
+
+// Todo snapshots for undo and redo, newest last.
+#[derive(Default)]
+struct History {
+    // The todos before each local change.
+    undo: Vec<BTreeMap<TodoId, Todo>>,
+    // Snapshots replaced by undo, cleared by any new change.
+    redo: Vec<BTreeMap<TodoId, Todo>>,
+}
+
+impl History {
+    // `previous` are the todos before a new local change, which can't be followed by a redo.
+    fn record(&mut self, previous: BTreeMap<TodoId, Todo>) {
+        push_bounded(&mut self.undo, previous);
+        self.redo.clear();
+    }
+
+    // Swaps `todos` for the last snapshot, returns whether there was one.
+    fn undo(&mut self, todos: &mut BTreeMap<TodoId, Todo>) -> bool {
+        Self::step(&mut self.undo, &mut self.redo, todos)
+    }
+
+    fn redo(&mut self, todos: &mut BTreeMap<TodoId, Todo>) -> bool {
+        Self::step(&mut self.redo, &mut self.undo, todos)
+    }
+
+    fn step(
+        from: &mut Vec<BTreeMap<TodoId, Todo>>,
+        to: &mut Vec<BTreeMap<TodoId, Todo>>,
+        todos: &mut BTreeMap<TodoId, Todo>,
+    ) -> bool {
+        match from.pop() {
+            Some(snapshot) => {
+                push_bounded(to, mem::replace(todos, snapshot));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Drop the oldest snapshot once the stack is full.
+fn push_bounded(stack: &mut Vec<BTreeMap<TodoId, Todo>>, snapshot: BTreeMap<TodoId, Todo>) {
+    if stack.len() >= HISTORY_LIMIT {
+        stack.remove(0);
+    }
+    stack.push(snapshot);
+}
+
+async fn fetch_remote(url: String) -> fetch::Result<RemoteTodos> {
+    fetch(url).await?.check_status()?.json().await
+}
+
+async fn push_local(request: Request<'static>) -> fetch::Result<()> {
+    request.fetch().await?.check_status()?;
+    Ok(())
+}
+
+fn save_todos(model: &mut Model) {
+    // Walking the whole storage is only worth it when the todos changed, not on every keystroke.
+    if model.todos != model.saved_todos {
+        let estimate = estimate_storage_bytes(&model.todos);
+        model.storage_warning = IF!(estimate > STORAGE_SOFT_LIMIT_BYTES => {
+            "Your todos are close to the browser's storage limit. \
+            Export them or move them to IndexedDB before saving fails."
+                .to_owned()
+        });
+    }
+
+    // Save anyway, the soft limit is only an estimate.
+    if let Err(error) = LocalStorage::insert(STORAGE_KEY, &model.todos) {
+        error!("Could not save todos into local storage", error);
+        model.storage_warning = Some(
+            "Saving failed, the browser storage is probably full. \
+            Export your todos or remove some of them."
+                .to_owned(),
+        );
+        change_save_status(model, SaveEvent::WriteFailed);
+    } else {
+        change_save_status(model, SaveEvent::Written);
+    }
+}
+
+// The absolute `#/todo/<id>` URL of the todo, under the route base.
+fn todo_link(base_url: &Url, id: TodoId) -> String {
+    let mut link = window().location().href().unwrap_or_default();
+    if let Some(index) = link.find('#') {
+        link.truncate(index);
+    }
+    link + &hash_link(base_url, &[TODO_LINK, &id.to_string()])
+}
+
+fn todo_element_id(id: TodoId) -> String {
+    format!("todo-{}", id)
+}
+
+// The todo whose row has the focus, if any.
+fn focused_todo_id() -> Option<TodoId> {
+    document()
+        .active_element()?
+        .id()
+        .strip_prefix("todo-")
+        .and_then(TodoId::parse)
+}
+
+// The index of the first todo at or after `from_index` (wrapping around)
+// whose title starts with `buffer`, ignoring case.
+fn find_type_ahead(todos: &[&Todo], buffer: &str, from_index: usize) -> Option<usize> {
+    if todos.is_empty() || buffer.is_empty() {
+        return None;
+    }
+    let buffer = buffer.to_lowercase();
+    (0..todos.len())
+        .map(|offset| (from_index + offset) % todos.len())
+        .find(|index| todos[*index].title.to_lowercase().starts_with(&buffer))
+}
+
+// `navigator.clipboard` isn't among the web-sys features seed enables, so it's called through `Reflect`.
+fn copy_to_clipboard(text: &str) -> Result<js_sys::Promise, JsValue> {
+    let clipboard = js_sys::Reflect::get(&window().navigator(), &JsValue::from_str("clipboard"))?;
+    let write_text: js_sys::Function =
+        js_sys::Reflect::get(&clipboard, &JsValue::from_str("writeText"))?.dyn_into()?;
+    write_text
+        .call1(&clipboard, &JsValue::from_str(text))?
+        .dyn_into()
+}
+
+// The cleared todos go into the current history entry and a new entry is pushed on top,
+// so Back lands on the entry holding them. Browsers limit the size of the entries, so they
+// come back without their voice notes.
+fn push_history_state(cleared: &BTreeMap<TodoId, Todo>) -> Result<(), JsValue> {
+    let mut cleared = cleared.clone();
+    for todo in cleared.values_mut() {
+        todo.audio = None;
+    }
+    let json =
+        serde_json::to_string(&cleared).map_err(|error| JsValue::from_str(&error.to_string()))?;
+    let history = window().history()?;
+    history.replace_state(&JsValue::from_str(&json), "")?;
+    history.push_state(&JsValue::NULL, "")
+}
+
+fn parse_history_state(json: &str) -> Option<BTreeMap<TodoId, Todo>> {
+    serde_json::from_str(json).ok()
+}
+
+// Saves `contents` as a file through a temporary `<a download>` link.
+fn download(file_name: &str, contents: &str) -> Result<(), JsValue> {
+    let blob =
+        web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(contents)))?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let link: web_sys::HtmlElement = document().create_element("a")?.dyn_into()?;
+    link.set_attribute("href", &url)?;
+    link.set_attribute("download", file_name)?;
+    // Firefox ignores clicks on links outside of the document.
+    let body = document().body().ok_or("The document has no body")?;
+    body.append_child(&link)?;
+    link.click();
+    body.remove_child(&link)?;
+    // The download may start after `click` returns, revoking right away could cancel it.
+    let revoke = Closure::once_into_js(move || {
+        if let Err(error) = web_sys::Url::revoke_object_url(&url) {
+            error!("Could not revoke the download URL", error);
+        }
+    });
+    window().set_timeout_with_callback_and_timeout_and_arguments_0(revoke.unchecked_ref(), 0)?;
+    Ok(())
+}
+
+// `FileList` isn't among the web-sys features seed enables.
+fn chosen_file(event: &web_sys::Event) -> Option<web_sys::File> {
+    let target: JsValue = event.target()?.into();
+    let files = js_sys::Reflect::get(&target, &JsValue::from_str("files")).ok()?;
+    js_sys::Reflect::get(&files, &JsValue::from_f64(0.))
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+fn read_clipboard() -> Result<js_sys::Promise, JsValue> {
+    let clipboard = js_sys::Reflect::get(&window().navigator(), &JsValue::from_str("clipboard"))?;
+    let read_text: js_sys::Function =
+        js_sys::Reflect::get(&clipboard, &JsValue::from_str("readText"))?.dyn_into()?;
+    read_text.call0(&clipboard)?.dyn_into()
+}
+
+fn call_method(target: &JsValue, name: &str) -> Result<JsValue, JsValue> {
+    let method: js_sys::Function =
+        js_sys::Reflect::get(target, &JsValue::from_str(name))?.dyn_into()?;
+    method.call0(target)
+}
+
+// `navigator.mediaDevices.getUserMedia({ audio: true })`, asking for the permission the first time.
+async fn open_microphone() -> Result<JsValue, JsValue> {
+    let media_devices =
+        js_sys::Reflect::get(&window().navigator(), &JsValue::from_str("mediaDevices"))?;
+    let get_user_media: js_sys::Function =
+        js_sys::Reflect::get(&media_devices, &JsValue::from_str("getUserMedia"))?.dyn_into()?;
+    let constraints = js_sys::Object::new();
+    js_sys::Reflect::set(&constraints, &JsValue::from_str("audio"), &JsValue::TRUE)?;
+    let promise: js_sys::Promise = get_user_media
+        .call1(&media_devices, &constraints)?
+        .dyn_into()?;
+    JsFuture::from(promise).await
+}
+
+// Without a timeslice the recorder delivers the whole recording once it's stopped.
+fn start_recorder(
+    stream: &JsValue,
+    on_data: impl FnOnce(web_sys::Blob) + 'static,
+) -> Result<JsValue, JsValue> {
+    let media_recorder: js_sys::Function =
+        js_sys::Reflect::get(&window(), &JsValue::from_str("MediaRecorder"))?.dyn_into()?;
+    let recorder = js_sys::Reflect::construct(&media_recorder, &js_sys::Array::of1(stream))?;
+    let on_data_available =
+        Closure::once_into_js(move |event: JsValue| {
+            match js_sys::Reflect::get(&event, &JsValue::from_str("data"))
+                .and_then(|data| data.dyn_into::<web_sys::Blob>().map_err(JsValue::from))
+            {
+                Ok(blob) => on_data(blob),
+                Err(error) => error!("The recording has no data", error),
+            }
+        });
+    js_sys::Reflect::set(
+        &recorder,
+        &JsValue::from_str("ondataavailable"),
+        &on_data_available,
+    )?;
+    call_method(&recorder, "start")?;
+    Ok(recorder)
+}
+
+// Releases the microphone, the browser's recording indicator goes away.
+fn stop_tracks(stream: &JsValue) {
+    let tracks = call_method(stream, "getTracks").map(|tracks| js_sys::Array::from(&tracks));
+    match tracks {
+        Ok(tracks) => {
+            for track in tracks.iter() {
+                if let Err(error) = call_method(&track, "stop") {
+                    error!("Could not stop a microphone track", error);
+                }
+            }
+        }
+        Err(error) => error!("Could not release the microphone", error),
+    }
+}
+
+fn fits_voice_note_cap(bytes: usize) -> bool {
+    bytes <= VOICE_NOTE_MAX_BYTES
+}
+
+// `data:<type>;base64,<data>`, `btoa` takes the bytes as a string of code points 0–255.
+async fn read_data_url(blob: web_sys::Blob) -> Result<String, JsValue> {
+    let buffer = JsFuture::from(blob.array_buffer()).await?;
+    let binary: String = js_sys::Uint8Array::new(&buffer)
+        .to_vec()
+        .into_iter()
+        .map(char::from)
+        .collect();
+    Ok(format!(
+        "data:{};base64,{}",
+        blob.type_(),
+        window().btoa(&binary)?
+    ))
+}
+
+fn start_pomodoro_ticker(orders: &mut impl Orders<Msg>) -> StreamHandle {
+    orders.stream_with_handle(streams::interval(1_000, || Msg::PomodoroTick))
+}
+
+// Counts, saves and hides a todo that was just completed, or un-hides a reopened one.
+fn completion_changed(model: &mut Model, orders: &mut impl Orders<Msg>, id: TodoId) {
+    if model.todos.get(&id).map_or(false, |todo| todo.completed) {
+        model.completed_today.roll_over(Day::today());
+        model.completed_today.count += 1;
+        if model.completed_today.reach_goal(model.settings.daily_goal) {
+            model.celebrating = true;
+        }
+        save_completed_today(&model.completed_today);
+        model.streak.record_completion(Day::today());
+        save_streak(&model.streak);
+        if complete_focus_task(&mut model.focus_task, id) {
+            save_focus_task(None);
+            if model.distraction_shield.is_some() {
+                orders.send_msg(Msg::ExitDistractionShield);
+            }
+        }
+        schedule_hide(model, orders, id);
+    } else {
+        model.pending_hides.remove(&id);
+        model.hidden_completed.remove(&id);
+    }
+}
+
+// Hide the just completed todo `id` once the configured delay has passed.
+// Completing it again restarts the delay, the previous timer is dropped.
+fn schedule_hide(model: &mut Model, orders: &mut impl Orders<Msg>, id: TodoId) {
+    if let Some(secs) = model.settings.auto_hide_completed_secs {
+        let handle = orders
+            .perform_cmd_with_handle(cmds::timeout(secs.saturating_mul(1_000), move || {
+                Msg::HideCompleted(id)
+            }));
+        model.pending_hides.insert(id, handle);
+    }
+}
+
+// The trimmed, non-empty titles typed into the new todo input.
+// Without a delimiter the whole input is a single title.
+fn split_titles(input: &str, delimiter: Option<char>) -> Vec<String> {
+    let segments: Vec<&str> = match delimiter {
+        Some(delimiter) => input.split(delimiter).collect(),
+        None => vec![input],
+    };
+    segments
+        .into_iter()
+        .map(str::trim)
+        .filter(|title| not(title.is_empty()))
+        .map(str::to_owned)
+        .collect()
+}
+
+// Upper-cases the first character, which may turn into several (e.g. "ß" → "SS").
+fn capitalize_first(title: &str) -> String {
+    let mut characters = title.chars();
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
+
+// Take the scheduled todos whose time has come.
+fn activate_due(scheduled: &mut BTreeMap<TodoId, (Todo, i64)>, now: i64) -> Vec<Todo> {
+    let due: Vec<TodoId> = scheduled
+        .iter()
+        .filter(|(_, (_, activate_at))| *activate_at <= now)
+        .map(|(id, _)| *id)
+        .collect();
+    due.iter()
+        .filter_map(|id| scheduled.remove(id))
+        .map(|(todo, _)| todo)
+        .collect()
+}
+
+#[derive(Copy, Clone, EnumIter)]
+enum SnoozePreset {
+    LaterToday,
+    TomorrowMorning,
+    ThisWeekend,
+    NextWeek,
+}
+
+impl SnoozePreset {
+    fn title(self) -> &'static str {
+        match self {
+            Self::LaterToday => "Later today (6pm)",
+            Self::TomorrowMorning => "Tomorrow morning (9am)",
+            Self::ThisWeekend => "This weekend (9am)",
+            Self::NextWeek => "Next week (Mon 9am)",
+        }
+    }
+}
+
+// `now` and the result are local times, see `local_ms`. "This weekend" is the next Saturday or
+// Sunday 9am, "Next week" is a week away on Mondays.
+// The result can be in the past, e.g. "Later today" in the evening.
+fn compute_snooze_time(preset: SnoozePreset, now: i64) -> i64 {
+    const MS_PER_HOUR: i64 = 3_600_000;
+    let ms_per_day = MS_PER_DAY as i64;
+    let today = now.div_euclid(ms_per_day);
+    // 0 is Sunday, the 1st of January 1970 was a Thursday.
+    let weekday = |day: i64| (day + 4).rem_euclid(7);
+    let at = |day: i64, hours: i64| day * ms_per_day + hours * MS_PER_HOUR;
+    match preset {
+        SnoozePreset::LaterToday => at(today, 18),
+        SnoozePreset::TomorrowMorning => at(today + 1, 9),
+        SnoozePreset::ThisWeekend => (today..)
+            .filter(|day| matches!(weekday(*day), 6 | 0))
+            .map(|day| at(day, 9))
+            .find(|time| *time > now)
+            .unwrap_or_default(),
+        SnoozePreset::NextWeek => {
+            let days = match (8 - weekday(today)) % 7 {
+                0 => 7,
+                days => days,
+            };
+            at(today + days, 9)
+        }
+    }
+}
+
+// The wall-clock time in milliseconds, as if the local time zone were UTC.
+fn local_ms(time: f64) -> i64 {
+    let offset = js_sys::Date::new(&JsValue::from_f64(time)).get_timezone_offset();
+    (time - offset * MS_PER_MINUTE) as i64
+}
+
+// The inverse of `local_ms`. The offset is looked up at the result, daylight saving time
+// may start or end between now and then.
+fn from_local_ms(local: i64) -> i64 {
+    let offset = |time: f64| js_sys::Date::new(&JsValue::from_f64(time)).get_timezone_offset();
+    let guess = local as f64 + offset(local as f64) * MS_PER_MINUTE;
+    (local as f64 + offset(guess) * MS_PER_MINUTE) as i64
+}
+
+fn reset_confirmed(typed: &str) -> bool {
+    typed == RESET_CONFIRMATION
+}
+
+// The oldest backlog todos are pulled first.
+fn pull_from_backlog(todos: &mut BTreeMap<TodoId, Todo>, count: usize) {
+    todos
+        .values_mut()
+        .filter(|todo| todo.backlog)
+        .take(count)
+        .for_each(|todo| todo.backlog = false);
+}
+
+// Adds the todos whose ids aren't taken yet, returns how many were added.
+fn add_new_todos(todos: &mut BTreeMap<TodoId, Todo>, new_todos: Vec<Todo>) -> usize {
+    let mut added = 0;
+    for todo in new_todos {
+        if not(todos.contains_key(&todo.id)) {
+            todos.insert(todo.id, todo);
+            added += 1;
+        }
+    }
+    added
+}
+
+// Moves the todo into the single undo slot, replacing an earlier deletion there.
+fn delete_todo<'a>(
+    todos: &mut BTreeMap<TodoId, Todo>,
+    last_deleted: &'a mut Option<Deleted>,
+    id: TodoId,
+) -> Option<&'a mut Deleted> {
+    let todo = todos.remove(&id)?;
+    Some(last_deleted.insert(Deleted {
+        todo,
+        timeout: None,
+    }))
+}
+
+// Unless its id has been taken meanwhile, e.g. by a sync.
+fn restore_deleted(todos: &mut BTreeMap<TodoId, Todo>, todo: Todo) {
+    todos.entry(todo.id).or_insert(todo);
+}
+
+fn active_count(todos: &BTreeMap<TodoId, Todo>) -> usize {
+    todos
+        .values()
+        .filter(|todo| Filter::Active.matches(todo))
+        .count()
+}
+
+// Why the completion of a todo can't be toggled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum ToggleRefusal {
+    // It has unfinished dependencies.
+    Blocked,
+    // Reopening it would exceed the WIP limit, completing is always allowed.
+    WipLimit,
+}
+
+fn toggle_refusal(
+    todos: &BTreeMap<TodoId, Todo>,
+    wip_limit: Option<usize>,
+    id: TodoId,
+) -> Option<ToggleRefusal> {
+    let todo = todos.get(&id)?;
+    if not(todo.completed) && todo.is_blocked(todos) {
+        Some(ToggleRefusal::Blocked)
+    } else if todo.completed && not(todo.backlog) && not(can_add_active(todos, wip_limit)) {
+        Some(ToggleRefusal::WipLimit)
+    } else {
+        None
+    }
+}
+
+// Whether one more active todo fits under the WIP limit.
+fn can_add_active(todos: &BTreeMap<TodoId, Todo>, wip_limit: Option<usize>) -> bool {
+    fits_wip_limit(todos, wip_limit, 1)
+}
+
+fn fits_wip_limit(
+    todos: &BTreeMap<TodoId, Todo>,
+    wip_limit: Option<usize>,
+    new_active: usize,
+) -> bool {
+    wip_limit.map_or(true, |limit| active_count(todos) + new_active <= limit)
+}
+
+// Applies the change unless it leaves more todos active than before and over the WIP limit,
+// all or nothing. Returns whether the change was applied.
+fn change_within_wip_limit(
+    todos: &mut BTreeMap<TodoId, Todo>,
+    wip_limit: Option<usize>,
+    change: impl FnOnce(&mut BTreeMap<TodoId, Todo>),
+) -> bool {
+    let mut changed = todos.clone();
+    change(&mut changed);
+    let (before, after) = (active_count(todos), active_count(&changed));
+    if after > before && not(fits_wip_limit(todos, wip_limit, after - before)) {
+        return false;
+    }
+    *todos = changed;
+    true
+}
+
+// `change_within_wip_limit` on the model's todos, a refusal shows the WIP limit notice.
+fn change_todos(
+    model: &mut Model,
+    orders: &mut impl Orders<Msg>,
+    change: impl FnOnce(&mut BTreeMap<TodoId, Todo>),
+) -> bool {
+    let changed = change_within_wip_limit(&mut model.todos, model.settings.wip_limit, change);
+    if not(changed) {
+        show_notice(model, orders, wip_limit_notice(model));
+    }
+    changed
+}
+
+fn wip_limit_notice(model: &Model) -> String {
+    format!(
+        "WIP limit of {} active todos reached, complete one first",
+        model.settings.wip_limit.unwrap_or_default()
+    )
+}
+
+fn show_notice(model: &mut Model, orders: &mut impl Orders<Msg>, text: impl Into<String>) {
+    model.notice = Some(Notice {
+        text: text.into(),
+        _timeout: orders
+            .perform_cmd_with_handle(cmds::timeout(NOTICE_TIMEOUT_MS, || Msg::DismissNotice)),
+    });
+}
+
+// The edited todo may have been removed meanwhile, e.g. in another tab.
+// Stop editing in that case and tell the user why.
+fn check_selected_todo_exists(model: &mut Model, orders: &mut impl Orders<Msg>) -> bool {
+    if forget_missing_selection(&mut model.selected_todo, &model.todos) {
+        show_notice(model, orders, "The todo you were editing was removed");
+        return false;
+    }
+    true
+}
+
+// Returns whether the selection was cleared.
+fn forget_missing_selection(
+    selected_todo: &mut Option<SelectedTodo>,
+    todos: &BTreeMap<TodoId, Todo>,
+) -> bool {
+    match selected_todo {
+        Some(selected) if not(todos.contains_key(&selected.id)) => {
+            *selected_todo = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn save_settings(settings: &Settings) {
+    if let Err(error) = LocalStorage::insert(SETTINGS_STORAGE_KEY, settings) {
+        error!("Could not save settings", error);
+    }
+}
+
+fn save_sync_version(version: u64) {
+    if let Err(error) = LocalStorage::insert(SYNC_VERSION_STORAGE_KEY, &version) {
+        error!("Could not save the sync version", error);
+    }
+}
+
+// Returns whether the completed todo was the focus task, which then ends.
+fn complete_focus_task(focus_task: &mut Option<TodoId>, completed: TodoId) -> bool {
+    if *focus_task != Some(completed) {
+        return false;
+    }
+    *focus_task = None;
+    true
+}
+
+// The banner shows the focus task until it's completed (e.g. in another tab) or removed.
+fn focus_banner_todo(focus_task: Option<TodoId>, todos: &BTreeMap<TodoId, Todo>) -> Option<&Todo> {
+    focus_task
+        .and_then(|id| todos.get(&id))
+        .filter(|todo| not(todo.completed))
+}
+
+// Stored with the day it was picked on.
+fn save_focus_task(focus_task: Option<TodoId>) {
+    let result = match focus_task {
+        Some(id) => LocalStorage::insert(FOCUS_TASK_STORAGE_KEY, &(id, Day::today())),
+        None => LocalStorage::remove(FOCUS_TASK_STORAGE_KEY),
+    };
+    if let Err(error) = result {
+        error!("Could not save the focus task", error);
+    }
+}
+
+fn save_sync_base(base: &RemoteTodos) {
+    let base = SyncPayload {
+        version: base.version,
+        todos: fingerprint_audio(&base.todos),
+    };
+    if let Err(error) = LocalStorage::insert(SYNC_BASE_STORAGE_KEY, &base) {
+        error!("Could not save the sync base", error);
+    }
+}
+
+// Keeps the log newest first. An entry logged at the same time as others goes before them,
+// it was logged last. Returns whether there was any text to log.
+fn log_done(done_log: &mut Vec<(i64, String)>, now: i64, text: &str) -> bool {
+    let text = text.trim();
+    if text.is_empty() {
+        return false;
+    }
+    let index = done_log.partition_point(|(timestamp, _)| *timestamp > now);
+    done_log.insert(index, (now, text.to_owned()));
+    true
+}
+
+// Logs saved before were oldest first.
+fn newest_first(mut done_log: Vec<(i64, String)>) -> Vec<(i64, String)> {
+    done_log.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+    done_log
+}
+
+fn save_done_log(done_log: &[(i64, String)]) {
+    if let Err(error) = LocalStorage::insert(DONE_LOG_STORAGE_KEY, &done_log) {
+        error!("Could not save the done log", error);
+    }
+}
+
+fn save_scheduled(scheduled: &BTreeMap<TodoId, (Todo, i64)>) {
+    if let Err(error) = LocalStorage::insert(SCHEDULED_STORAGE_KEY, scheduled) {
+        error!("Could not save the scheduled todos", error);
+    }
+}
+
+fn save_streak(streak: &Streak) {
+    if let Err(error) = LocalStorage::insert(STREAK_STORAGE_KEY, streak) {
+        error!("Could not save the streak", error);
+    }
+}
+
+fn save_completed_today(completed_today: &CompletedToday) {
+    if let Err(error) = LocalStorage::insert(COMPLETED_TODAY_STORAGE_KEY, completed_today) {
+        error!("Could not save the completed today counter", error);
+    }
+}
+
+// The quota is shared by every key of the origin (sync base, activity, done log, settings, ...),
+// so count all of them.
+fn estimate_storage_bytes(todos: &BTreeMap<TodoId, Todo>) -> usize {
+    let todos_json = serde_json::to_string(todos).unwrap_or_default();
+    let Some(storage) = window().local_storage().ok().flatten() else {
+        return storage_bytes(&todos_json, Vec::new());
+    };
+    let length = storage.length().unwrap_or_default();
+    let entries = (0..length)
+        .filter_map(|index| storage.key(index).ok().flatten())
+        .map(|key| {
+            let value = storage.get_item(&key).ok().flatten().unwrap_or_default();
+            (key, value)
+        });
+    storage_bytes(&todos_json, entries)
+}
+
+// The todos are counted as they're about to be saved, their stored entry is ignored.
+fn storage_bytes(todos_json: &str, entries: impl IntoIterator<Item = (String, String)>) -> usize {
+    let others: usize = entries
+        .into_iter()
+        .filter(|(key, _)| key != STORAGE_KEY)
+        .map(|(key, value)| utf16_bytes(&key) + utf16_bytes(&value))
+        .sum();
+    utf16_bytes(todos_json) + others
+}
+
+// `localStorage` keeps strings as UTF-16, i.e. two bytes per code unit.
+fn utf16_bytes(text: &str) -> usize {
+    text.encode_utf16().count() * 2
+}
+
+// Cut the selected part out of `title` and return `(rest, extracted)`, both trimmed.
+// `start` and `end` are UTF-16 offsets as reported by the input's selection API.
+// Returns `None` when nothing (or only whitespace) is selected, or when nothing would be left,
+// since saving an emptied title removes the todo.
+fn split_selection(title: &str, start: u32, end: u32) -> Option<(String, String)> {
+    let byte_index = |offset: u32| {
+        let mut units = 0;
+        for (index, character) in title.char_indices() {
+            if units == offset {
+                return Some(index);
+            }
+            units += u32::try_from(character.len_utf16()).ok()?;
+        }
+        (units == offset).then_some(title.len())
+    };
+    let (start, end) = (byte_index(start)?, byte_index(end)?);
+
+    let extracted = title.get(start..end)?.trim();
+    if extracted.is_empty() {
+        return None;
+    }
+    let rest = format!("{}{}", &title[..start], &title[end..]);
+    let rest = rest.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    Some((rest.to_owned(), extracted.to_owned()))
+}
+
+// ------ shortcuts ------
+
+// Global shortcuts are ignored while the user types into an input (new todo or edit),
+// so they never clobber regular text editing.
+fn shortcut_msg(keyboard_event: &web_sys::KeyboardEvent) -> Option<Msg> {
+    if is_typing(keyboard_event) {
+        return None;
+    }
+    let has_modifier =
+        keyboard_event.ctrl_key() || keyboard_event.alt_key() || keyboard_event.meta_key();
+    let key = keyboard_event.key();
+    // Letters jump to matching todos while a row has the focus.
+    if not(has_modifier) && in_todo_list(keyboard_event) {
+        let mut characters = key.chars();
+        if let (Some(character), None) = (characters.next(), characters.next()) {
+            if not(character.is_whitespace()) {
+                // E.g. `/` would open Firefox's quick find.
+                keyboard_event.prevent_default();
+                return Some(Msg::TypeAhead(character));
+            }
+        }
+    }
+    // The keymap lives in the settings, so `update` resolves the action.
+    key_combo(keyboard_event).map(Msg::Shortcut)
+}
+
+fn in_todo_list(keyboard_event: &web_sys::KeyboardEvent) -> bool {
+    keyboard_event
+        .target()
+        .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+        .and_then(|element| element.closest(".todo-list").ok().flatten())
+        .is_some()
+}
+
+fn is_typing(keyboard_event: &web_sys::KeyboardEvent) -> bool {
+    keyboard_event
+        .target()
+        .and_then(|target| target.dyn_into::<web_sys::HtmlElement>().ok())
+        .map_or(false, |element| {
+            let input_type = element
+                .dyn_ref::<web_sys::HtmlInputElement>()
+                .map(web_sys::HtmlInputElement::type_);
+            is_text_entry(
+                &element.tag_name(),
+                input_type.as_deref(),
+                element.is_content_editable(),
+            )
+        })
+}
+
+// Checkboxes, sliders and buttons keep the focus after a click, they don't take text.
+// `input_type` is the input's normalized type, e.g. "text" when the attribute is missing.
+fn is_text_entry(tag_name: &str, input_type: Option<&str>, content_editable: bool) -> bool {
+    match tag_name {
+        "TEXTAREA" => true,
+        "INPUT" => matches!(
+            input_type,
+            Some(
+                "text"
+                    | "search"
+                    | "email"
+                    | "url"
+                    | "tel"
+                    | "number"
+                    | "password"
+                    | "date"
+                    | "datetime-local"
+                    | "month"
+                    | "week"
+                    | "time"
+            )
+        ),
+        _ => content_editable,
+    }
+}
+
+// ------ ------
+//     View
+// ------ ------
+
+// `view` describes what to display.
+fn view(model: &Model) -> Vec<Node<Msg>> {
+    if model.printing {
+        return nodes![view_print(&model.todos)];
+    }
+    if model.minimized {
+        return nodes![view_mini(&model.todos)];
+    }
+    if let (Some(shield), Some(todo)) = (
+        &model.distraction_shield,
+        model.focus_task.and_then(|id| model.todos.get(&id)),
+    ) {
+        return nodes![view_distraction_shield(todo, shield)];
+    }
+    nodes![
+        model
+            .storage_warning
+            .as_ref()
+            .map(|warning| div![C!["storage-warning"], warning]),
+        model
+            .notice
+            .as_ref()
+            .map(|notice| div![C!["notice"], &notice.text]),
+        model
+            .last_deleted
+            .as_ref()
+            .map(|deleted| view_undo_delete(&deleted.todo)),
+        IF!(not(model.conflicts.is_empty()) => view_conflicts(&model.conflicts, &model.todos)),
+        view_header(
+            &model.new_todo_title,
+            &model.new_todo_input,
+            model.settings.sync_url.as_ref().map(|_| model.sync_status),
+            IF!(model.settings.show_save_status => &model.save_status),
+            model
+                .pomodoro
+                .as_ref()
+                .map(|pomodoro| view_pomodoro(pomodoro, &model.todos)),
+        ),
+        focus_banner_todo(model.focus_task, &model.todos).map(view_focus_banner),
+        IF!(model.celebrating => view_goal_celebration(model.completed_today.count)),
+        IF!(not(model.new_todo_title.trim().is_empty() && model.scheduled.is_empty()) =>
+            view_schedule(&model.new_todo_title, &model.schedule_at, &model.scheduled)
+        ),
+        IF!(model.todos.is_empty() => view_empty_onboarding()),
+        match (&model.focus_trap, model.settings_open) {
+            (Some(focus_trap), true) => Some(view_settings(
+                focus_trap,
+                &model.settings,
+                IF!(model.activity_open => model.activity.as_slice()),
+                model.rebinding.as_ref(),
+                model.reset_confirmation.as_deref()
+            )),
+            _ => None,
+        },
+        IF!(not(model.todos.is_empty()) => view_main(
+            &model.todos,
+            model.filter,
+            model.backlog_pull_count,
+            view_todo_list(
+                &model.todos,
+                model.selected_todo.as_ref(),
+                model.filter,
+                &model.hidden_completed,
+                model.highlighted_todo.as_ref().map(|highlight| highlight.id),
+                IF!(model.settings.summarize_completed => model.completed_summary_expanded),
+                &model.settings,
+            )
+        )),
+        view_done_log(&model.done_log),
+        IF!(not(model.todos.is_empty()) => view_footer(
+            &model.todos,
+            model.filter,
+            model.completed_today.count,
+            model.streak.current(Day::today()),
+            model.settings.daily_capacity,
+            model.settings.wip_limit,
+            &model.base_url,
+        )),
+    ]
+}
+
+fn view_header(
+    new_todo_title: &str,
+    new_todo_input: &ElRef<web_sys::HtmlInputElement>,
+    sync_status: Option<SyncStatus>,
+    save_status: Option<&SaveStatus>,
+    pomodoro: Option<Node<Msg>>,
+) -> Node<Msg> {
+    header![
+        C!["header"],
+        h1!["todos"],
+        sync_status.map(view_sync_status),
+        save_status.and_then(view_save_status),
+        pomodoro,
+        button![
+            C!["minimize-toggle"],
+            attrs! {At::Title => "Minimize"},
+            "▁",
+            ev(Ev::Click, |_| Msg::ToggleMinimized)
+        ],
+        button![
+            C!["settings-toggle"],
+            attrs! {At::Title => "Settings"},
+            "⚙",
+            ev(Ev::Click, |_| Msg::ToggleSettings)
+        ],
+        input![
+            C!["new-todo"],
+            el_ref(new_todo_input),
+            attrs! {At::Placeholder => "What needs to be done?", At::AutoFocus => AtValue::None, At::Value => new_todo_title},
+            input_ev(Ev::Input, Msg::NewTodoTitleChanged),
+            keyboard_ev(Ev::KeyDown, |keyboard_event| {
+                IF!(keyboard_event.key() == ENTER_KEY => Msg::CreateTodo)
+            })
+        ]
+    ]
+}
+
+fn view_sync_status(sync_status: SyncStatus) -> Node<Msg> {
+    let (class, text) = match sync_status {
+        SyncStatus::Idle => ("idle", "Synced"),
+        SyncStatus::Syncing => ("syncing", "Syncing…"),
+        SyncStatus::Error => ("error", "Sync failed"),
+    };
+    span![
+        C!["sync-status", class],
+        text,
+        IF!(sync_status == SyncStatus::Error => ev(Ev::Click, |_| Msg::FetchRemote)),
+        IF!(sync_status == SyncStatus::Error => attrs! {At::Title => "Click to retry"}),
+    ]
+}
+
+fn view_save_status(save_status: &SaveStatus) -> Option<Node<Msg>> {
+    let (class, text) = match save_status {
+        SaveStatus::Idle => return None,
+        SaveStatus::Saved(_) => ("saved", "Saved"),
+        SaveStatus::Failed => ("failed", "Save failed"),
+    };
+    Some(span![
+        C!["save-status", class],
+        attrs! {At::from("role") => "status"},
+        text
+    ])
+}
+
+// Presets already in the past aren't offered.
+fn view_snooze_menu(id: TodoId) -> Node<Msg> {
+    let now = js_sys::Date::now();
+    let times: Vec<(SnoozePreset, i64)> = SnoozePreset::iter()
+        .map(|preset| {
+            let local = compute_snooze_time(preset, local_ms(now));
+            (preset, from_local_ms(local))
+        })
+        .filter(|(_, time)| *time > now as i64)
+        .collect();
+    select![
+        C!["snooze"],
+        attrs! {At::Title => "Snooze"},
+        option![
+            attrs! {At::Value => "", At::Selected => true.as_at_value()},
+            "💤"
+        ],
+        times
+            .iter()
+            .map(|(preset, time)| { option![attrs! {At::Value => time}, preset.title()] }),
+        input_ev(Ev::Change, move |time| {
+            time.parse().ok().map(|time| Msg::SnoozeTodo(id, time))
+        }),
+    ]
+}
+
+fn view_focus_banner(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    div![
+        C!["focus-banner"],
+        span![C!["focus-banner-label"], "Today's focus"],
+        strong![&todo.title],
+        button!["Done", ev(Ev::Click, move |_| Msg::ToggleTodo(id))],
+        button![
+            attrs! {At::Title => "Full screen with only this todo"},
+            "Shield",
+            ev(Ev::Click, |_| Msg::EnterDistractionShield)
+        ],
+        button![
+            attrs! {At::Title => "Clear today's focus"},
+            "✕",
+            ev(Ev::Click, |_| Msg::SetFocusTask(None))
+        ],
+    ]
+}
+
+fn view_distraction_shield(todo: &Todo, shield: &DistractionShield) -> Node<Msg> {
+    let id = todo.id;
+    div![
+        C!["distraction-shield"],
+        span![C!["focus-banner-label"], "Today's focus"],
+        h2![&todo.title],
+        span![
+            C!["distraction-shield-time"],
+            format_elapsed(shield.started_at, js_sys::Date::now() as i64)
+        ],
+        div![
+            button!["Done", ev(Ev::Click, move |_| Msg::ToggleTodo(id))],
+            button!["Exit", ev(Ev::Click, |_| Msg::ExitDistractionShield)],
+        ],
+    ]
+}
+
+// `mm:ss` since `started_at` (ms), minutes keep counting past an hour.
+fn format_elapsed(started_at: i64, now: i64) -> String {
+    let elapsed_secs = (now - started_at).max(0) / 1_000;
+    format!("{:02}:{:02}", elapsed_secs / 60, elapsed_secs % 60)
+}
+
+fn view_goal_celebration(completed_today: u32) -> Node<Msg> {
+    div![
+        C!["goal-celebration"],
+        attrs! {At::from("role") => "status"},
+        span![C!["confetti"], "🎉"],
+        format!("Daily goal reached: {} todos done today!", completed_today),
+        button![
+            attrs! {At::Title => "Dismiss"},
+            "✕",
+            ev(Ev::Click, |_| Msg::DismissCelebration)
+        ],
+    ]
+}
+
+fn view_pomodoro(pomodoro: &Pomodoro, todos: &BTreeMap<TodoId, Todo>) -> Node<Msg> {
+    let phase = match pomodoro.phase {
+        PomodoroPhase::Focus => "🍅",
+        PomodoroPhase::Break => "☕",
+    };
+    div![
+        C!["pomodoro"],
+        span![
+            C!["pomodoro-time"],
+            format!(
+                "{} {:02}:{:02}",
+                phase,
+                pomodoro.remaining_secs / 60,
+                pomodoro.remaining_secs % 60
+            )
+        ],
+        todos
+            .get(&pomodoro.todo_id)
+            .map(|todo| span![C!["pomodoro-todo"], &todo.title]),
+        button![
+            if pomodoro.ticker.is_some() {
+                "Pause"
+            } else {
+                "Resume"
+            },
+            ev(Ev::Click, |_| Msg::TogglePomodoro)
+        ],
+        button!["Reset", ev(Ev::Click, |_| Msg::ResetPomodoro)],
+    ]
+}
+
+// "Schedule for…" shows up once a title has been typed, pending todos are listed below it.
+fn view_schedule(
+    new_todo_title: &str,
+    schedule_at: &str,
+    scheduled: &BTreeMap<TodoId, (Todo, i64)>,
+) -> Node<Msg> {
+    section![
+        C!["schedule"],
+        IF!(not(new_todo_title.trim().is_empty()) => label![
+            "Schedule for…",
+            input![
+                attrs! {At::Type => "datetime-local", At::Value => schedule_at},
+                input_ev(Ev::Input, Msg::ScheduleAtChanged),
+            ],
+            button![
+                attrs! {At::Disabled => schedule_at.is_empty().as_at_value()},
+                "Schedule",
+                ev(Ev::Click, |_| Msg::ScheduleNewTodo)
+            ],
+        ]),
+        ul![scheduled.values().map(|(todo, activate_at)| {
+            let id = todo.id;
+            li![
+                span![C!["activity-time"], format_timestamp(*activate_at)],
+                &todo.title,
+                button![
+                    attrs! {At::Title => "Add it now"},
+                    "Now",
+                    ev(Ev::Click, move |_| Msg::ActivateScheduledNow(id))
+                ],
+                button![
+                    attrs! {At::Title => "Cancel"},
+                    "✕",
+                    ev(Ev::Click, move |_| Msg::CancelScheduled(id))
+                ],
+            ]
+        })],
+    ]
+}
+
+fn view_empty_onboarding() -> Node<Msg> {
+    section![
+        C!["onboarding"],
+        p![C!["onboarding-illustration"], "📝"],
+        p!["Nothing to do yet. Type a todo above and press Enter, e.g. \"Buy milk\"."],
+        button!["Add sample todos", ev(Ev::Click, |_| Msg::AddSamples)],
+    ]
+}
+
+fn view_main(
+    todos: &BTreeMap<TodoId, Todo>,
+    filter: Filter,
+    backlog_pull_count: usize,
+    todo_list: Node<Msg>,
+) -> Node<Msg> {
+    section![
+        C!["main"],
+        view_toggle_all(todos),
+        IF!(filter == Filter::Backlog => view_backlog_pull(backlog_pull_count)),
+        todo_list,
+    ]
+}
+
+fn view_toggle_all(todos: &BTreeMap<TodoId, Todo>) -> Vec<Node<Msg>> {
+    let all_completed = all_completed(todos);
+    vec![
+        input![
+            C!["toggle-all"],
+            attrs! {At::Id => "toggle-all", At::Type => "checkbox", At::Checked => all_completed.as_at_value()},
+            ev(Ev::Change, |_| Msg::CheckOrUncheckAll)
+        ],
+        label![
+            attrs! {At::For => "toggle-all", At::TabIndex => 0},
+            "Mark all as complete",
+            keyboard_ev(Ev::KeyDown, |keyboard_event| {
+                match keyboard_event.key().as_str() {
+                    SPACE_KEY | ENTER_KEY => {
+                        // Don't let the document shortcut handler toggle a second time.
+                        keyboard_event.stop_propagation();
+                        keyboard_event.prevent_default();
+                        Some(Msg::CheckOrUncheckAll)
+                    }
+                    _ => None,
+                }
+            }),
+        ],
+    ]
+}
+
+fn view_backlog_pull(backlog_pull_count: usize) -> Node<Msg> {
+    div![
+        C!["backlog-pull"],
+        input![
+            attrs! {At::Type => "number", At::Min => 1, At::Value => backlog_pull_count},
+            input_ev(Ev::Input, Msg::BacklogPullCountChanged),
+        ],
+        button!["Pull from backlog", ev(Ev::Click, |_| Msg::PullFromBacklog)],
+    ]
+}
+
+fn view_todo_list(
+    todos: &BTreeMap<TodoId, Todo>,
+    selected_todo: Option<&SelectedTodo>,
+    filter: Filter,
+    hidden_completed: &BTreeSet<TodoId>,
+    highlighted_todo: Option<TodoId>,
+    // `Some(expanded)` when the completed todos are summarized.
+    completed_summary: Option<bool>,
+    settings: &Settings,
+) -> Node<Msg> {
+    let completed_summary = completed_summary.filter(|_| filter == Filter::All);
+    let visible = list_order(
+        visible_todos(todos, filter, hidden_completed).collect(),
+        settings.sort_by_points,
+    );
+    let summarized_count = summarized_completed_count(&visible);
+    let collapsed = completed_summary == Some(false);
+
+    let items: Vec<Node<Msg>> = visible
+        .iter()
+        .filter(|todo| not(collapsed && todo.completed))
+        .map(|todo| {
+            let id = todo.id;
+            let is_selected = Some(id) == selected_todo.map(|selected_todo| selected_todo.id);
+            let is_blocked = todo.is_blocked(todos);
+            li![
+                C![
+                    IF!(todo.completed => "completed"),
+                    IF!(is_selected => "editing"),
+                    IF!(is_blocked => "blocked"),
+                    IF!(highlighted_todo == Some(id) => "highlighted")
+                ],
+                el_key(&todo.id),
+                // Focusable for type-ahead, but not a Tab stop.
+                attrs! {At::Id => todo_element_id(id), At::TabIndex => -1},
+                div![
+                    C!["view"],
+                    input![
+                        C!["toggle"],
+                        attrs! {
+                            At::Type => "checkbox",
+                            At::Checked => todo.completed.as_at_value(),
+                            At::Disabled => (is_blocked && not(todo.completed)).as_at_value(),
+                        },
+                        IF!(is_blocked => attrs! {At::Title => "Blocked by unfinished todos"}),
+                        ev(Ev::Change, move |_| Msg::ToggleTodo(id))
+                    ],
+                    label![
+                        todo.icon.as_ref().map(|icon| span![C!["icon"], icon]),
+                        &todo.title,
+                        todo.points
+                            .map(|points| span![C!["todo-points"], points.to_string()]),
+                        IF!(not(todo.checklist.is_empty()) => {
+                            let (checked, total) = todo.checklist_progress();
+                            span![C!["checklist-progress"], format!("{}/{}", checked, total)]
+                        }),
+                        ev(Ev::DblClick, move |_| Msg::SelectTodo(Some(id)))
+                    ],
+                    button![
+                        C!["copy-link"],
+                        attrs! {At::Title => "Copy link"},
+                        "🔗",
+                        ev(Ev::Click, move |_| Msg::CopyTodoLink(id))
+                    ],
+                    view_snooze_menu(id),
+                    button![
+                        C!["focus-start"],
+                        attrs! {At::Title => "Make this today's focus"},
+                        "☆",
+                        ev(Ev::Click, move |_| Msg::SetFocusTask(Some(id)))
+                    ],
+                    button![
+                        C!["pomodoro-start"],
+                        attrs! {At::Title => "Start a focus session"},
+                        "🍅",
+                        ev(Ev::Click, move |_| Msg::StartPomodoro(id))
+                    ],
+                    button![
+                        C!["backlog"],
+                        if todo.backlog {
+                            "Activate"
+                        } else {
+                            "Move to backlog"
+                        },
+                        ev(Ev::Click, move |_| Msg::ToggleBacklog(id))
+                    ],
+                    button![C!["destroy"], ev(Ev::Click, move |_| Msg::RemoveTodo(id))],
+                ],
+                IF!(todo.progress() > 0 && not(todo.completed) => div![
+                    C!["progress-bar"],
+                    div![style! {St::Width => format!("{}%", todo.progress())}],
+                ]),
+                IF!(is_selected => view_todo_edit(todo, selected_todo.unwrap(), todos))
+            ]
+        })
+        .collect();
+    let summary = completed_summary
+        .filter(|_| summarized_count > 0)
+        .map(|expanded| view_completed_summary(summarized_count, expanded));
+
+    view_todo_list_element(settings.numbered_list, items, summary)
+}
+
+// Only the rendered rows are numbered, so the numbers follow the filter.
+fn view_todo_list_element(
+    numbered: bool,
+    items: Vec<Node<Msg>>,
+    summary: Option<Node<Msg>>,
+) -> Node<Msg> {
+    if numbered {
+        ol![C!["todo-list", "numbered"], items, summary]
+    } else {
+        ul![C!["todo-list"], items, summary]
+    }
+}
+
+// The completed todos the summary row stands for, the footer counts stay the same.
+fn summarized_completed_count(visible: &[&Todo]) -> usize {
+    visible.iter().filter(|todo| todo.completed).count()
+}
+
+fn view_completed_summary(count: usize, expanded: bool) -> Node<Msg> {
+    li![
+        C!["completed-summary"],
+        format!(
+            "{} completed — {}",
+            format_count(count),
+            if expanded { "hide" } else { "show" }
+        ),
+        ev(Ev::Click, |_| Msg::ToggleCompletedSummary),
+    ]
+}
+
+// Auto-hidden todos stay out of All until they're un-completed (e.g. by "Mark all").
+fn visible_todos<'a>(
+    todos: &'a BTreeMap<TodoId, Todo>,
+    filter: Filter,
+    hidden_completed: &'a BTreeSet<TodoId>,
+) -> impl Iterator<Item = &'a Todo> {
+    todos.values().filter(move |todo| {
+        filter.matches(todo)
+            && not(filter == Filter::All && todo.completed && hidden_completed.contains(&todo.id))
+    })
+}
+
+// Sorting by points only changes what the list shows, the stored order stays.
+// Todos without points come last, equal points keep their order.
+fn list_order(mut todos: Vec<&Todo>, sort_by_points: bool) -> Vec<&Todo> {
+    if sort_by_points {
+        todos.sort_by_key(|todo| std::cmp::Reverse(todo.points));
+    }
+    todos
+}
+
+fn view_todo_edit(
+    todo: &Todo,
+    selected_todo: &SelectedTodo,
+    todos: &BTreeMap<TodoId, Todo>,
+) -> Vec<Node<Msg>> {
+    nodes![
+        todo.icon.as_ref().map(|icon| span![C!["edit-icon"], icon]),
+        input![
+            C!["edit"],
+            el_ref(&selected_todo.input_element),
+            attrs! {At::Value => selected_todo.title},
+            input_ev(Ev::Input, Msg::SelectedTodoTitleChanged),
+            keyboard_ev(Ev::KeyDown, |keyboard_event| {
+                match keyboard_event.key().as_str() {
+                    ESC_KEY => Some(Msg::EscapeSelectedTodo),
+                    // Ctrl+Enter moves the selected text into a new todo.
+                    ENTER_KEY if keyboard_event.ctrl_key() => Some(Msg::SplitSelection),
+                    ENTER_KEY => Some(Msg::SaveSelectedTodo),
+                    _ => None,
+                }
+            }),
+            ev(
+                Ev::Blur,
+                |event| IF!(leaves_edit_view(&event) => Msg::SaveSelectedTodo)
+            ),
+        ],
+        view_related_todos(&related_todos(todo, todos, RELATED_TODO_COUNT), todos),
+        view_icon_picker(todo),
+        view_points_picker(todo),
+        view_progress_slider(todo),
+        view_start_date_picker(todo),
+        view_dependency_picker(todo, todos),
+        view_checklist(todo),
+        view_voice_note(todo, selected_todo.recording.is_some()),
+        view_comments(todo),
+    ]
+}
+
+// Moving the focus between the title and the comment input keeps the todo in edit mode,
+// leaving the row saves it.
+fn leaves_edit_view(event: &web_sys::Event) -> bool {
+    // `FocusEvent` isn't among the web-sys features seed enables.
+    js_sys::Reflect::get(event, &JsValue::from_str("relatedTarget"))
+        .ok()
+        .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+        .and_then(|target| target.closest("li.editing").ok().flatten())
+        .is_none()
+}
+
+// The buttons act on `mousedown` with the default prevented, so the edit input keeps focus.
+fn view_voice_note(todo: &Todo, recording: bool) -> Node<Msg> {
+    let id = todo.id;
+    let (text, msg): (_, fn(TodoId) -> Msg) = match (recording, todo.audio.is_some()) {
+        (true, _) => ("■ Stop", |_| Msg::StopVoiceNote),
+        (false, true) => ("● Record again", Msg::StartVoiceNote),
+        (false, false) => ("● Record a voice note", Msg::StartVoiceNote),
+    };
+    div![
+        C!["voice-note", IF!(recording => "recording")],
+        todo.audio.as_ref().map(|audio| {
+            audio![attrs! {At::Src => audio.as_ref(), At::Controls => AtValue::None}]
+        }),
+        button![
+            text,
+            mouse_ev(Ev::MouseDown, move |mouse_event| {
+                mouse_event.prevent_default();
+                msg(id)
+            }),
+        ],
+        IF!(todo.audio.is_some() && not(recording) => button![
+            "Remove",
+            mouse_ev(Ev::MouseDown, move |mouse_event| {
+                mouse_event.prevent_default();
+                Msg::RemoveVoiceNote(id)
+            }),
+        ]),
+        span![
+            C!["voice-note-hint"],
+            format!(
+                "Up to {} s, stored in the browser with your todos",
+                VOICE_NOTE_MAX_SECS
+            )
+        ],
+    ]
+}
+
+// Items are toggled and removed on `mousedown`, like the pickers below.
+fn view_checklist(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    div![
+        C!["checklist"],
+        ul![todo
+            .checklist
+            .iter()
+            .enumerate()
+            .map(|(index, (text, checked))| {
+                li![
+                    C![IF!(*checked => "checked")],
+                    span![
+                        C!["checklist-item"],
+                        if *checked { "☑ " } else { "☐ " },
+                        text,
+                        mouse_ev(Ev::MouseDown, move |mouse_event| {
+                            mouse_event.prevent_default();
+                            Msg::ToggleChecklistItem(id, index)
+                        }),
+                    ],
+                    span![
+                        C!["checklist-remove"],
+                        attrs! {At::Title => "Remove the item"},
+                        "✕",
+                        mouse_ev(Ev::MouseDown, move |mouse_event| {
+                            mouse_event.prevent_default();
+                            Msg::RemoveChecklistItem(id, index)
+                        }),
+                    ],
+                ]
+            })],
+        input![
+            attrs! {At::Placeholder => "Add a checklist item"},
+            keyboard_ev(Ev::KeyDown, move |keyboard_event| {
+                match keyboard_event.key().as_str() {
+                    ESC_KEY => Some(Msg::EscapeSelectedTodo),
+                    ENTER_KEY => {
+                        let input = keyboard_event
+                            .target()?
+                            .dyn_into::<web_sys::HtmlInputElement>()
+                            .ok()?;
+                        let text = input.value();
+                        input.set_value("");
+                        Some(Msg::AddChecklistItem(id, text))
+                    }
+                    _ => None,
+                }
+            }),
+            ev(
+                Ev::Blur,
+                |event| IF!(leaves_edit_view(&event) => Msg::SaveSelectedTodo)
+            ),
+        ],
+    ]
+}
+
+// Newest first.
+fn view_comments(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    div![
+        C!["comments"],
+        input![
+            attrs! {At::Placeholder => "Add a comment"},
+            keyboard_ev(Ev::KeyDown, move |keyboard_event| {
+                match keyboard_event.key().as_str() {
+                    ESC_KEY => Some(Msg::EscapeSelectedTodo),
+                    ENTER_KEY => {
+                        let input = keyboard_event
+                            .target()?
+                            .dyn_into::<web_sys::HtmlInputElement>()
+                            .ok()?;
+                        let text = input.value();
+                        input.set_value("");
+                        Some(Msg::AddComment(id, text))
+                    }
+                    _ => None,
+                }
+            }),
+            ev(
+                Ev::Blur,
+                |event| IF!(leaves_edit_view(&event) => Msg::SaveSelectedTodo)
+            ),
+        ],
+        ul![todo.comments_newest_first().map(|(timestamp, text)| {
+            li![
+                span![C!["activity-time"], format_timestamp(*timestamp)],
+                text,
+            ]
+        })],
+    ]
+}
+
+// Buttons, so moving the focus to one doesn't save the edit on blur (see `leaves_edit_view`).
+fn view_related_todos(related: &[TodoId], todos: &BTreeMap<TodoId, Todo>) -> Option<Node<Msg>> {
+    if related.is_empty() {
+        return None;
+    }
+    Some(div![
+        C!["related"],
+        span!["Related:"],
+        related.iter().filter_map(|id| todos.get(id)).map(|other| {
+            let other_id = other.id;
+            button![
+                &other.title,
+                ev(Ev::Click, move |_| Msg::SelectTodo(Some(other_id)))
+            ]
+        }),
+    ])
+}
+
+// Same `mousedown` trick as the dependency picker below.
+fn view_icon_picker(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    div![
+        C!["picker", "icons"],
+        span!["Icon:"],
+        ul![
+            ICONS.iter().map(|icon| {
+                let is_selected = todo.icon.as_deref() == Some(*icon);
+                li![
+                    C![IF!(is_selected => "selected")],
+                    icon,
+                    mouse_ev(Ev::MouseDown, move |mouse_event| {
+                        mouse_event.prevent_default();
+                        Msg::SetTodoIcon(id, IF!(not(is_selected) => (*icon).to_owned()))
+                    }),
+                ]
+            }),
+            IF!(todo.icon.is_some() => li![
+                attrs! {At::Title => "Remove the icon"},
+                "✕",
+                mouse_ev(Ev::MouseDown, move |mouse_event| {
+                    mouse_event.prevent_default();
+                    Msg::SetTodoIcon(id, None)
+                }),
+            ]),
+        ]
+    ]
+}
+
+fn view_progress_slider(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    label![
+        C!["progress-slider"],
+        "Progress:",
+        input![
+            attrs! {
+                At::Type => "range",
+                At::Min => 0,
+                At::Max => 100,
+                At::Step => 5,
+                At::Value => todo.progress(),
+            },
+            input_ev(Ev::Change, move |progress| {
+                progress
+                    .parse()
+                    .ok()
+                    .map(|progress| Msg::SetProgress(id, progress))
+            }),
+            ev(
+                Ev::Blur,
+                |event| IF!(leaves_edit_view(&event) => Msg::SaveSelectedTodo)
+            ),
+        ],
+        span![format!("{}%", todo.progress())],
+    ]
+}
+
+// Clearing the date input removes the start date.
+fn view_start_date_picker(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    label![
+        C!["start-date"],
+        "Starts on:",
+        input![
+            attrs! {
+                At::Type => "date",
+                At::Value => todo.start.map(Day::to_iso_date).unwrap_or_default(),
+            },
+            input_ev(Ev::Change, move |date| Msg::SetStartDate(
+                id,
+                Day::from_iso_date(&date)
+            )),
+            ev(
+                Ev::Blur,
+                |event| IF!(leaves_edit_view(&event) => Msg::SaveSelectedTodo)
+            ),
+        ],
+    ]
+}
+
+fn view_points_picker(todo: &Todo) -> Node<Msg> {
+    let id = todo.id;
+    div![
+        C!["picker", "points-picker"],
+        span!["Points:"],
+        ul![POINTS.iter().map(|points| {
+            let points = *points;
+            let is_selected = todo.points == Some(points);
+            li![
+                C![IF!(is_selected => "selected")],
+                points.to_string(),
+                mouse_ev(Ev::MouseDown, move |mouse_event| {
+                    mouse_event.prevent_default();
+                    Msg::SetTodoPoints(id, IF!(not(is_selected) => points))
+                }),
+            ]
+        })]
+    ]
+}
+
+// Picking happens on `mousedown` with the default prevented,
+// so the edit input keeps focus and doesn't save on blur.
+fn view_dependency_picker(todo: &Todo, todos: &BTreeMap<TodoId, Todo>) -> Node<Msg> {
+    let id = todo.id;
+    let candidates = todos.values().filter(|other| other.id != id);
+    div![
+        C!["dependencies"],
+        span!["Blocked by:"],
+        ul![candidates.map(|other| {
+            let dependency_id = other.id;
+            let is_dependency = todo.blocked_by.contains(&dependency_id);
+            let disabled = not(is_dependency) && would_create_cycle(todos, id, dependency_id);
+            li![
+                C![
+                    IF!(is_dependency => "selected"),
+                    IF!(disabled => "disabled")
+                ],
+                &other.title,
+                IF!(not(disabled) => mouse_ev(Ev::MouseDown, move |mouse_event| {
+                    mouse_event.prevent_default();
+                    Msg::ToggleDependency(id, dependency_id)
+                })),
+            ]
+        })]
+    ]
+}
+
+fn view_undo_delete(todo: &Todo) -> Node<Msg> {
+    div![
+        C!["undo-delete"],
+        format!("Deleted “{}”", todo.title),
+        button!["Undo", ev(Ev::Click, |_| Msg::RestoreDeleted)],
+    ]
+}
+
+// ------ conflicts ------
+
+fn view_conflicts(conflicts: &[Conflict], todos: &BTreeMap<TodoId, Todo>) -> Node<Msg> {
+    div![
+        C!["conflicts"],
+        h2!["Sync conflicts"],
+        ul![conflicts.iter().filter_map(|conflict| {
+            let todo = todos.get(&conflict.id)?;
+            let id = conflict.id;
+            let (description, keep, replace) = match &conflict.kind {
+                ConflictKind::Fields(fields, _) => (
+                    format!(
+                        "{} changed on both devices",
+                        fields
+                            .iter()
+                            .map(|field| field.title())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    "Keep mine",
+                    "Use theirs",
+                ),
+                ConflictKind::DeletedRemotely => (
+                    "deleted on another device, changed here".to_owned(),
+                    "Keep",
+                    "Delete",
+                ),
+                ConflictKind::DeletedLocally => (
+                    "deleted here, changed on another device".to_owned(),
+                    "Restore",
+                    "Delete",
+                ),
+            };
+            Some(li![
+                strong![&todo.title],
+                span![C!["conflict-description"], description],
+                button![
+                    keep,
+                    ev(Ev::Click, move |_| Msg::ResolveConflict(
+                        id,
+                        Resolution::Keep
+                    ))
+                ],
+                button![
+                    replace,
+                    ev(Ev::Click, move |_| Msg::ResolveConflict(
+                        id,
+                        Resolution::Override
+                    ))
+                ],
+            ])
+        })]
+    ]
+}
+
+// ------ done log ------
+
+// Today's entries only, the log doesn't count towards the todo totals.
+fn view_done_log(done_log: &[(i64, String)]) -> Node<Msg> {
+    let today = Day::today();
+    let entries_today = done_log.iter().filter(|(timestamp, _)| {
+        Day::from_date(&js_sys::Date::new(&JsValue::from_f64(*timestamp as f64))) == today
+    });
+    section![
+        C!["done-log"],
+        input![
+            attrs! {At::Placeholder => "Log done work"},
+            keyboard_ev(Ev::KeyDown, |keyboard_event| {
+                if keyboard_event.key() != ENTER_KEY {
+                    return None;
+                }
+                let input = keyboard_event
+                    .target()?
+                    .dyn_into::<web_sys::HtmlInputElement>()
+                    .ok()?;
+                let text = input.value();
+                input.set_value("");
+                Some(Msg::LogDone(text))
+            }),
+        ],
+        ul![entries_today.map(|(_, text)| li![text])],
+    ]
+}
+
+// ------ footer ------
+
+fn view_footer(
+    todos: &BTreeMap<TodoId, Todo>,
+    selected_filter: Filter,
+    completed_today: u32,
+    streak: u32,
+    daily_capacity: u8,
+    wip_limit: Option<usize>,
+    base_url: &Url,
+) -> Node<Msg> {
+    // Backlog todos don't count towards the totals.
+    let completed_count = todos
+        .values()
+        .filter(|todo| Filter::Completed.matches(todo))
+        .count();
+    let active_count = active_count(todos);
+
+    footer![
+        C!["footer"],
+        // This should be `0 items left` by default
+        span![
+            C!["todo-count"],
+            strong![format_count(active_count)],
+            items_left_label(active_count),
+        ],
+        IF!(completed_today > 0 => span![C!["completed-today"], format!("✅ {} today", completed_today)]),
+        IF!(streak > 1 => span![C!["streak"], format!("🔥 {}-day streak", streak)]),
+        view_points(total_points(todos), daily_capacity),
+        view_average_progress(todos),
+        wip_limit.map(|limit| span![
+            C!["wip", IF!(active_count >= limit => "at-limit")],
+            format!("WIP {}/{}", active_count, limit)
+        ]),
+        view_filters(selected_filter, base_url),
+        IF!(completed_count > 0 => button![C!["clear-completed"], "Clear completed", ev(Ev::Click, |_| Msg::ClearCompleted)]),
+        button![C!["print"], "Print", ev(Ev::Click, |_| Msg::Print)],
+    ]
+}
+
+// Shown once an active todo has some progress, completed todos count as 100%.
+fn view_average_progress(todos: &BTreeMap<TodoId, Todo>) -> Option<Node<Msg>> {
+    let todos: Vec<&Todo> = todos
+        .values()
+        .filter(|todo| Filter::All.matches(todo))
+        .collect();
+    if not(todos
+        .iter()
+        .any(|todo| not(todo.completed) && todo.progress > 0))
+    {
+        return None;
+    }
+    let total: usize = todos.iter().map(|todo| usize::from(todo.progress())).sum();
+    Some(span![
+        C!["average-progress"],
+        format!("{}% done", total / todos.len())
+    ])
+}
+
+fn view_points(points: u32, daily_capacity: u8) -> Option<Node<Msg>> {
+    let over_capacity = daily_capacity > 0 && points > u32::from(daily_capacity);
+    let text = match (points, daily_capacity) {
+        (0, _) => return None,
+        (points, 0) => format!("{} pts", points),
+        (points, capacity) if over_capacity => {
+            format!("{}/{} pts — over capacity", points, capacity)
+        }
+        (points, capacity) => format!("{}/{} pts", points, capacity),
+    };
+    Some(span![
+        C!["points", IF!(over_capacity => "over-capacity")],
+        text
+    ])
+}
+
+fn view_filters(selected_filter: Filter, base_url: &Url) -> Node<Msg> {
+    ul![
+        C!["filters"],
+        Filter::iter().map(|filter| {
+            let (link, title) = filter.link_and_title();
+            li![a![
+                C![IF!(filter == selected_filter => "selected")],
+                attrs! { At::Href => hash_link(base_url, &[link]) },
+                title
+            ]]
+        })
+    ]
+}
+
+// ------ locale ------
+
+thread_local! {
+    // Built once, the footer formats its counts on every render.
+    // `None` in browsers without `Intl`.
+    static NUMBER_FORMAT: Option<js_sys::Intl::NumberFormat> = IF!(intl_available() => {
+        js_sys::Intl::NumberFormat::new(&browser_locales(), &js_sys::Object::new())
+    });
+    static PLURAL_RULES: Option<js_sys::Intl::PluralRules> = IF!(intl_available() => {
+        js_sys::Intl::PluralRules::new(&browser_locales(), &js_sys::Object::new())
+    });
+}
+
+// Counts use the browser's number format (e.g. "1,234" vs "1 234").
+// Browsers without `Intl` get the plain formatting.
+fn format_count(count: usize) -> String {
+    NUMBER_FORMAT
+        .with(|number_format| {
+            number_format
+                .as_ref()?
+                .format()
+                .call1(&JsValue::UNDEFINED, &JsValue::from_f64(count as f64))
+                .ok()?
+                .as_string()
+        })
+        .unwrap_or_else(|| count.to_string())
+}
+
+// Date and time in the browser's format, or ISO 8601 without `Intl`.
+fn format_timestamp(timestamp: i64) -> String {
+    let date = js_sys::Date::new(&JsValue::from_f64(timestamp as f64));
+    if not(intl_available()) {
+        return String::from(date.to_iso_string());
+    }
+    let options = js_sys::Object::new();
+    for (key, value) in &[("dateStyle", "short"), ("timeStyle", "short")] {
+        // Setting a property on a new plain object can't fail.
+        let _ = js_sys::Reflect::set(&options, &JsValue::from_str(key), &JsValue::from_str(value));
+    }
+    js_sys::Intl::DateTimeFormat::new(&browser_locales(), &options)
+        .format()
+        .call1(&JsValue::UNDEFINED, &date)
+        .ok()
+        .and_then(|formatted| formatted.as_string())
+        .unwrap_or_else(|| String::from(date.to_iso_string()))
+}
+
+// The plural category ("one", "few", "other", …) of the count in the browser's language.
+// Browsers without `Intl` get the English rules.
+fn plural_category(count: usize) -> String {
+    PLURAL_RULES
+        .with(|plural_rules| {
+            plural_rules
+                .as_ref()
+                .map(|plural_rules| String::from(plural_rules.select(count as f64)))
+        })
+        .unwrap_or_else(|| english_plural_category(count).to_owned())
+}
+
+fn english_plural_category(count: usize) -> &'static str {
+    if count == 1 {
+        "one"
+    } else {
+        "other"
+    }
+}
+
+fn items_left_label(count: usize) -> &'static str {
+    items_left_label_for(&plural_category(count))
+}
+
+// The label only has a singular and a plural form, every other category reads as plural.
+fn items_left_label_for(plural_category: &str) -> &'static str {
+    if plural_category == "one" {
+        " item left"
+    } else {
+        " items left"
+    }
+}
+
+fn browser_locales() -> js_sys::Array {
+    window()
+        .navigator()
+        .language()
+        .map_or_else(js_sys::Array::new, |language| {
+            js_sys::Array::of1(&JsValue::from_str(&language))
+        })
+}
+
+fn intl_available() -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("Intl")).unwrap_or(false)
+}
+
+// ------ settings ------
+
+fn view_settings(
+    focus_trap: &FocusTrap,
+    settings: &Settings,
+    activity: Option<&[ActivityEntry]>,
+    rebinding: Option<&Rebinding>,
+    reset_confirmation: Option<&str>,
+) -> Node<Msg> {
+    view_modal(
+        focus_trap,
+        "settings",
+        "Settings",
+        || Msg::ToggleSettings,
+        nodes![
+            h2!["Settings"],
+            label![
+                "Start on",
+                select![
+                    Filter::iter().map(|filter| {
+                        let (link, title) = filter.link_and_title();
+                        option![
+                            attrs! {
+                                At::Value => link,
+                                At::Selected => (filter == settings.default_filter).as_at_value(),
+                            },
+                            title
+                        ]
+                    }),
+                    input_ev(Ev::Change, Msg::DefaultFilterChanged),
+                ],
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.auto_capitalize.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleAutoCapitalize),
+                ],
+                "Capitalize todos",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.summarize_completed.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleSummarizeCompleted),
+                ],
+                "Summarize completed todos in All",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.numbered_list.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleNumberedList),
+                ],
+                "Number the todos",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.sort_by_points.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleSortByPoints),
+                ],
+                "Sort by points, most first",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.complete_with_checklist.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleCompleteWithChecklist),
+                ],
+                "Complete todos with their checklist",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.confirm_unsaved_edit.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleConfirmUnsavedEdit),
+                ],
+                "Ask before closing with an unsaved edit",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.esc_saves.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleEscSaves),
+                ],
+                "Escape saves the edit instead of cancelling it",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.history_undo.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleHistoryUndo),
+                ],
+                "Browser Back restores cleared completed todos",
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox", At::Checked => settings.show_save_status.as_at_value()},
+                    ev(Ev::Change, |_| Msg::ToggleShowSaveStatus),
+                ],
+                "Show when the todos are saved",
+            ],
+            label![
+                "Daily capacity (points)",
+                input![
+                    attrs! {
+                        At::Type => "number",
+                        At::Min => 0,
+                        At::Max => u8::MAX,
+                        At::Placeholder => "none",
+                        At::Value => IF!(settings.daily_capacity > 0 => settings.daily_capacity).unwrap_or_default(),
+                    },
+                    input_ev(Ev::Input, Msg::DailyCapacityChanged),
+                ],
+            ],
+            label![
+                "Split new todos on",
+                input![
+                    attrs! {
+                        At::MaxLength => 1,
+                        At::Size => 1,
+                        At::Placeholder => ",",
+                        At::Value => settings.split_delimiter.map(String::from).unwrap_or_default(),
+                    },
+                    input_ev(Ev::Input, Msg::SplitDelimiterChanged),
+                ],
+            ],
+            label![
+                "Max active todos (WIP limit)",
+                input![
+                    attrs! {
+                        At::Type => "number",
+                        At::Min => 1,
+                        At::Placeholder => "none",
+                        At::Value => settings.wip_limit.map(|limit| limit.to_string()).unwrap_or_default(),
+                    },
+                    input_ev(Ev::Input, Msg::WipLimitChanged),
+                ],
+            ],
+            label![
+                "Daily goal (completed todos)",
+                input![
+                    attrs! {
+                        At::Type => "number",
+                        At::Min => 1,
+                        At::Placeholder => "none",
+                        At::Value => settings.daily_goal.map(|goal| goal.to_string()).unwrap_or_default(),
+                    },
+                    input_ev(Ev::Input, Msg::DailyGoalChanged),
+                ],
+            ],
+            label![
+                "Hide completed todos after (seconds)",
+                input![
+                    attrs! {
+                        At::Type => "number",
+                        At::Min => 1,
+                        At::Placeholder => "never",
+                        At::Value => settings.auto_hide_completed_secs.map(|secs| secs.to_string()).unwrap_or_default(),
+                    },
+                    input_ev(Ev::Input, Msg::AutoHideCompletedChanged),
+                ],
+            ],
+            label![
+                "Ids of new todos",
+                select![
+                    option![
+                        attrs! {At::Value => "ulid", At::Selected => (settings.id_kind == IdKind::Ulid).as_at_value()},
+                        "ULID"
+                    ],
+                    option![
+                        attrs! {At::Value => "uuid", At::Selected => (settings.id_kind == IdKind::Uuid).as_at_value()},
+                        "UUID (no creation order)"
+                    ],
+                    input_ev(Ev::Change, Msg::IdKindChanged),
+                ],
+            ],
+            label![
+                "Sync URL",
+                input![
+                    attrs! {
+                        At::Type => "url",
+                        At::Placeholder => "https://example.com/todos",
+                        At::Value => settings.sync_url.as_deref().unwrap_or_default(),
+                    },
+                    input_ev(Ev::Change, Msg::SyncUrlChanged),
+                ],
+            ],
+            button![
+                "Import from other TodoMVC apps",
+                ev(Ev::Click, |_| Msg::MigrateLegacy)
+            ],
+            button!["Paste to import", ev(Ev::Click, |_| Msg::PasteImport)],
+            button![
+                "Export CSV",
+                ev(Ev::Click, |_| Msg::ExportCsv(ExportScope::All))
+            ],
+            button![
+                "Export visible as CSV",
+                ev(Ev::Click, |_| Msg::ExportCsv(ExportScope::Visible))
+            ],
+            label![
+                C!["csv-import"],
+                "Import CSV",
+                input![
+                    attrs! {At::Type => "file", At::Accept => ".csv,text/csv"},
+                    ev(Ev::Change, |event| Msg::CsvFileChosen(chosen_file(&event))),
+                ],
+            ],
+            button![
+                if activity.is_some() {
+                    "Hide activity"
+                } else {
+                    "Show activity"
+                },
+                ev(Ev::Click, |_| Msg::ToggleActivity)
+            ],
+            activity.map(view_activity),
+            view_keymap(&settings.keymap, rebinding),
+            view_reset(reset_confirmation),
+            button![
+                C!["settings-close"],
+                "Close",
+                ev(Ev::Click, |_| Msg::ToggleSettings)
+            ],
+        ],
+    )
+}
+
+// Newest first.
+fn view_activity(activity: &[ActivityEntry]) -> Node<Msg> {
+    if activity.is_empty() {
+        return p![C!["activity"], "Nothing has happened yet."];
+    }
+    ul![
+        C!["activity"],
+        activity.iter().rev().map(|entry| {
+            let kind = match entry.kind {
+                ActivityKind::Create => "Created",
+                ActivityKind::Complete => "Completed",
+                ActivityKind::Rename => "Renamed",
+                ActivityKind::Delete => "Deleted",
+            };
+            li![
+                span![C!["activity-time"], format_timestamp(entry.timestamp)],
+                format!("{}: {}", kind, entry.detail),
+            ]
+        })
+    ]
+}
+
+// Every combo is a button that rebinds just that combo, "Add" binds another one.
+fn view_keymap(keymap: &BTreeMap<String, Action>, rebinding: Option<&Rebinding>) -> Node<Msg> {
+    let defaults;
+    let keymap = if keymap.is_empty() {
+        defaults = default_keymap();
+        &defaults
+    } else {
+        keymap
+    };
+    div![
+        C!["keymap"],
+        h3!["Shortcuts"],
+        table![Action::iter().map(|action| {
+            let is_rebinding = |replaced: Option<&str>| {
+                rebinding.map_or(false, |rebinding| {
+                    rebinding.action == action && rebinding.replaced.as_deref() == replaced
+                })
+            };
+            let combos = keymap
+                .iter()
+                .filter(|(_, bound)| **bound == action)
+                .map(|(combo, _)| {
+                    let replaced = combo.clone();
+                    button![
+                        C!["keymap-combo"],
+                        attrs! {At::Title => "Rebind"},
+                        if is_rebinding(Some(combo)) {
+                            "Press a key…"
+                        } else {
+                            combo.as_str()
+                        },
+                        ev(Ev::Click, move |_| Msg::StartRebind(action, Some(replaced)))
+                    ]
+                });
+            tr![
+                td![action.title()],
+                td![combos],
+                td![button![
+                    if is_rebinding(None) {
+                        "Press a key…"
+                    } else {
+                        "Add"
+                    },
+                    ev(Ev::Click, move |_| Msg::StartRebind(action, None))
+                ]],
+            ]
+        })],
+        // Sequences can't be rebound.
+        table![Sequence::iter().map(|sequence| {
+            let (first, second) = sequence.keys();
+            tr![
+                td![sequence.title()],
+                td![C!["keymap-combo"], format!("{} then {}", first, second)],
+                td![],
+            ]
+        })],
+        button!["Reset shortcuts", ev(Ev::Click, |_| Msg::ResetKeymap)],
+    ]
+}
+
+fn view_reset(reset_confirmation: Option<&str>) -> Node<Msg> {
+    let typed = match reset_confirmation {
+        Some(typed) => typed,
+        None => {
+            return button![
+                C!["danger"],
+                "Delete all data",
+                ev(Ev::Click, |_| Msg::StartReset)
+            ]
+        }
+    };
+    div![
+        C!["reset-confirmation"],
+        p![format!(
+            "This removes all todos and settings. Type {} to confirm.",
+            RESET_CONFIRMATION
+        )],
+        input![
+            attrs! {At::Value => typed, At::Placeholder => RESET_CONFIRMATION},
+            input_ev(Ev::Input, Msg::ResetConfirmationChanged),
+        ],
+        button![
+            C!["danger"],
+            attrs! {At::Disabled => not(reset_confirmed(typed)).as_at_value()},
+            "Delete everything",
+            ev(Ev::Click, |_| Msg::ResetAll)
+        ],
+        button!["Cancel", ev(Ev::Click, |_| Msg::CancelReset)],
+    ]
+}
+
+// ------ modal ------
+
+// A dialog on a backdrop. Escape sends `on_close`, Tab wraps around inside `content`.
+fn view_modal(
+    focus_trap: &FocusTrap,
+    class: &str,
+    label: &str,
+    on_close: fn() -> Msg,
+    content: Vec<Node<Msg>>,
+) -> Node<Msg> {
+    let container = focus_trap.container.clone();
+    div![
+        C!["modal-overlay"],
+        div![
+            C![class],
+            el_ref(&focus_trap.container),
+            attrs! {At::from("role") => "dialog", At::from("aria-modal") => "true", At::from("aria-label") => label},
+            keyboard_ev(Ev::KeyDown, move |keyboard_event| {
+                match keyboard_event.key().as_str() {
+                    ESC_KEY => Some(on_close()),
+                    TAB_KEY => {
+                        trap_tab(&keyboard_event, &container);
+                        None
+                    }
+                    _ => None,
+                }
+            }),
+            content,
+        ]
+    ]
+}
+
+// Only the wrap-around at both ends is handled, the browser moves the focus in between.
+fn trap_tab(keyboard_event: &web_sys::KeyboardEvent, container: &ElRef<web_sys::HtmlElement>) {
+    let focusable = focusable_elements(container);
+    let (first, last) = match (focusable.first(), focusable.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return,
+    };
+    let is_active = |element: &web_sys::HtmlElement| {
+        document()
+            .active_element()
+            .map_or(false, |active| active.is_same_node(Some(element)))
+    };
+    let target = if keyboard_event.shift_key() {
+        IF!(is_active(first) => last)
+    } else {
+        IF!(is_active(last) => first)
+    };
+    if let Some(target) = target {
+        keyboard_event.prevent_default();
+        focus(target);
+    }
+}
+
+fn focusable_elements(container: &ElRef<web_sys::HtmlElement>) -> Vec<web_sys::HtmlElement> {
+    let nodes = match container
+        .get()
+        .and_then(|container| container.query_selector_all(FOCUSABLE_SELECTOR).ok())
+    {
+        Some(nodes) => nodes,
+        None => return Vec::new(),
+    };
+    (0..nodes.length())
+        .filter_map(|index| nodes.item(index)?.dyn_into().ok())
+        .collect()
+}
+
+fn focus(element: &web_sys::HtmlElement) {
+    if let Err(error) = element.focus() {
+        error!("Could not move the focus", error);
+    }
+}
+
+// ------ mini ------
+
+// The compact widget: active count, the first few active todos to complete, and a way back.
+fn view_mini(todos: &BTreeMap<TodoId, Todo>) -> Node<Msg> {
+    let active_count = active_count(todos);
+    section![
+        C!["mini"],
+        div![
+            C!["mini-summary"],
+            attrs! {At::Title => "Expand"},
+            strong![format_count(active_count)],
+            items_left_label(active_count),
+            ev(Ev::Click, |_| Msg::ToggleMinimized),
+        ],
+        ul![todos
+            .values()
+            .filter(|todo| Filter::Active.matches(todo))
+            .take(MINI_TODO_COUNT)
+            .map(|todo| {
+                let id = todo.id;
+                let is_blocked = todo.is_blocked(todos);
+                li![label![
+                    input![
+                        attrs! {
+                            At::Type => "checkbox",
+                            At::Disabled => is_blocked.as_at_value(),
+                        },
+                        ev(Ev::Change, move |_| Msg::ToggleTodo(id)),
+                    ],
+                    todo.icon.as_ref().map(|icon| span![C!["icon"], icon]),
+                    &todo.title,
+                ],]
+            })],
+    ]
+}
+
+// ------ print ------
+
+// A plain checklist of the active todos without app chrome or event handlers.
+fn view_print(todos: &BTreeMap<TodoId, Todo>) -> Node<Msg> {
+    section![
+        C!["print-view"],
+        h1!["todos"],
+        ul![todos
+            .values()
+            .filter(|todo| Filter::Active.matches(todo))
+            .map(|todo| {
+                li![
+                    input![attrs! {At::Type => "checkbox", At::Disabled => AtValue::None}],
+                    label![&todo.title],
+                ]
+            })]
+    ]
+}
+
+// ------ ------
+//     Start
+// ------ ------
+
+// The handle returned by `App::start`.
+pub type TodoApp = App<Msg, Model, Vec<Node<Msg>>>;
+
+thread_local! {
+    // Filled by `start`, so host apps can send messages in through `dispatch`.
+    static APP: RefCell<Option<TodoApp>> = RefCell::new(None);
+}
+
+// (This function is invoked by `init` function in `index.html`.)
+#[wasm_bindgen(start)]
+pub fn start() {
+    console_error_panic_hook::set_once();
+
+    let root_element = match find_or_create_root() {
+        Some(root_element) => root_element,
+        None => {
+            error!("Could not find .todoapp and there is no <body> to create it in, the app isn't started");
+            return;
+        }
+    };
+
+    let app = App::start(root_element, init, update, view);
+    APP.with(|cell| cell.replace(Some(app)));
+}
+
+// The app mounts into the first `.todoapp` element of the host page.
+// When there is none, a `<section class="todoapp">` is appended to `<body>` as a fallback,
+// so a forgotten mount point shows up as a logged error instead of a WASM trap.
+fn find_or_create_root() -> Option<web_sys::Element> {
+    if let Some(root_element) = document().get_elements_by_class_name("todoapp").item(0) {
+        return Some(root_element);
+    }
+    error!("Could not find .todoapp, appending it to <body>");
+    let root_element = document().create_element("section").ok()?;
+    root_element.set_class_name("todoapp");
+    document().body()?.append_child(&root_element).ok()?;
+    Some(root_element)
+}
+
+// Marks the interactive elements of the app that have no accessible name and logs them.
+// Each element is logged once, when it gets the marker.
+fn audit_accessibility() {
+    let root = match document().get_elements_by_class_name("todoapp").item(0) {
+        Some(root) => root,
+        None => return,
+    };
+    let elements = match root.query_selector_all(AUDITED_SELECTOR) {
+        Ok(elements) => elements,
+        Err(error) => {
+            error!("Could not query the elements to audit", error);
+            return;
+        }
+    };
+    for index in 0..elements.length() {
+        let element = match elements
+            .item(index)
+            .and_then(|node| node.dyn_into::<web_sys::Element>().ok())
+        {
+            Some(element) => element,
+            None => continue,
+        };
+        let missing = not(has_accessible_name(&element));
+        let marked = element.has_attribute(A11Y_MISSING_ATTRIBUTE);
+        let result = match (missing, marked) {
+            (true, false) => {
+                log!("Accessibility audit: no accessible name", element);
+                element.set_attribute(A11Y_MISSING_ATTRIBUTE, "")
+            }
+            (false, true) => element.remove_attribute(A11Y_MISSING_ATTRIBUTE),
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            error!("Could not mark the audited element", error);
+        }
+    }
+}
+
+// A rough approximation of the accessible name computation, good enough to spot gaps.
+fn has_accessible_name(element: &web_sys::Element) -> bool {
+    let has_attribute = |name: &str| {
+        element
+            .get_attribute(name)
+            .map_or(false, |value| not(value.trim().is_empty()))
+    };
+    if has_attribute("aria-label") || has_attribute("aria-labelledby") || has_attribute("title") {
+        return true;
+    }
+    let tag_name = element.tag_name();
+    if matches!(tag_name.as_str(), "INPUT" | "SELECT" | "TEXTAREA") {
+        let labelled_by_id = not(element.id().is_empty())
+            && document()
+                .query_selector(&format!("label[for=\"{}\"]", element.id()))
+                .ok()
+                .flatten()
+                .is_some();
+        return labelled_by_id
+            || element.closest("label").ok().flatten().is_some()
+            || has_attribute("placeholder");
+    }
+    element
+        .text_content()
+        .map_or(false, |text| not(text.trim().is_empty()))
+}
+
+// Send a `Msg` to the running app, e.g. `dispatch(Msg::ClearCompleted)` from a parent component.
+// Messages sent before `start` has been called are dropped.
+pub fn dispatch(msg: Msg) {
+    APP.with(|cell| {
+        if let Some(app) = cell.borrow().as_ref() {
+            app.update(msg);
+        }
+    });
 }
 
 // ------ ------
-//     Start
-// ------ ------
+//     Tests
+// ------ ------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> TodoId {
+        TodoId::Ulid(Ulid(n))
+    }
+
+    fn todo(n: u128, title: &str) -> Todo {
+        Todo::new(id(n), title.to_owned())
+    }
+
+    fn todos(list: Vec<Todo>) -> BTreeMap<TodoId, Todo> {
+        list.into_iter().map(|todo| (todo.id, todo)).collect()
+    }
+
+    #[test]
+    fn next_filter_cycles_all_active_completed() {
+        assert_eq!(next_filter(Filter::All), Filter::Active);
+        assert_eq!(next_filter(Filter::Active), Filter::Completed);
+        assert_eq!(next_filter(Filter::Completed), Filter::All);
+    }
+
+    #[test]
+    fn next_filter_continues_at_all_outside_the_cycle() {
+        assert_eq!(next_filter(Filter::Backlog), Filter::All);
+        assert_eq!(next_filter(Filter::Upcoming), Filter::All);
+    }
+
+    #[test]
+    fn split_selection_cuts_out_the_selected_part() {
+        assert_eq!(
+            split_selection("Buy milk and eggs", 9, 17),
+            Some(("Buy milk".to_owned(), "and eggs".to_owned()))
+        );
+    }
+
+    #[test]
+    fn split_selection_counts_utf16_offsets() {
+        // 🥛 is two UTF-16 code units.
+        assert_eq!(
+            split_selection("🥛 milk, 🥚 eggs", 9, 16),
+            Some(("🥛 milk,".to_owned(), "🥚 eggs".to_owned()))
+        );
+    }
+
+    #[test]
+    fn split_selection_needs_a_non_blank_selection() {
+        assert_eq!(split_selection("Buy milk", 3, 3), None);
+        assert_eq!(split_selection("Buy milk", 3, 4), None);
+        // Past the end or inside a surrogate pair.
+        assert_eq!(split_selection("Buy milk", 0, 20), None);
+        assert_eq!(split_selection("🥛 milk", 1, 3), None);
+    }
+
+    #[test]
+    fn split_selection_keeps_part_of_the_title() {
+        assert_eq!(split_selection("Buy milk", 0, 8), None);
+        assert_eq!(split_selection("  Buy milk ", 2, 10), None);
+    }
+
+    #[test]
+    fn complete_all_skips_blocked_todos() {
+        let mut blocked = todo(1, "Ship");
+        blocked.blocked_by = vec![id(3)];
+        let mut backlog = todo(3, "Someday");
+        backlog.backlog = true;
+        let mut list = todos(vec![blocked, todo(2, "Test"), backlog]);
+
+        complete_all(&mut list);
+
+        assert!(not(list[&id(1)].completed));
+        assert!(list[&id(2)].completed);
+        assert!(not(list[&id(3)].completed));
+        // What's left can't be completed, so the toggle unchecks next time.
+        assert!(all_completed(&list));
+    }
+
+    #[test]
+    fn complete_all_unblocks_dependents_of_completed_todos() {
+        let mut ship = todo(1, "Ship");
+        ship.blocked_by = vec![id(2)];
+        let mut list = todos(vec![ship, todo(2, "Test")]);
+        assert!(not(all_completed(&list)));
+
+        complete_all(&mut list);
+
+        assert!(list.values().all(|todo| todo.completed));
+    }
+
+    #[test]
+    fn wip_limit_counts_new_active_todos() {
+        let mut done = todo(3, "Done");
+        done.completed = true;
+        let list = todos(vec![todo(1, "One"), todo(2, "Two"), done]);
+
+        assert!(fits_wip_limit(&list, None, 100));
+        // Under the limit.
+        assert!(can_add_active(&list, Some(3)));
+        assert!(fits_wip_limit(&list, Some(4), 1));
+        // Exactly at the limit.
+        assert!(fits_wip_limit(&list, Some(4), 2));
+        assert!(not(can_add_active(&list, Some(2))));
+        // Over the limit.
+        assert!(not(fits_wip_limit(&list, Some(4), 3)));
+        assert!(not(can_add_active(&list, Some(1))));
+    }
+
+    #[test]
+    fn storage_bytes_counts_every_other_entry() {
+        let entries = vec![
+            (STORAGE_KEY.to_owned(), "stale todos".to_owned()),
+            ("settings".to_owned(), "{}".to_owned()),
+        ];
+        // The todos JSON, then "settings" and "{}", the stale todos don't count.
+        assert_eq!(storage_bytes("[]", entries), 4 + 16 + 4);
+        assert_eq!(storage_bytes("", Vec::new()), 0);
+    }
+
+    #[test]
+    fn utf16_bytes_counts_code_units() {
+        assert_eq!(utf16_bytes(""), 0);
+        assert_eq!(utf16_bytes("milk"), 8);
+        // One code unit for 'é', a surrogate pair for the emoji.
+        assert_eq!(utf16_bytes("é🥛"), 6);
+    }
+
+    fn payload(version: u64, list: Vec<Todo>) -> RemoteTodos {
+        SyncPayload {
+            version,
+            todos: todos(list),
+        }
+    }
+
+    #[test]
+    fn merge_remote_compares_versions() {
+        let local = todos(vec![todo(1, "Milk")]);
+
+        let newer = merge_remote(2, &local, None, payload(3, vec![todo(1, "Eggs")]));
+        assert!(matches!(newer, Merge::TakeRemote(remote) if remote.version == 3));
+
+        let older = merge_remote(2, &local, None, payload(1, vec![todo(1, "Eggs")]));
+        assert!(matches!(older, Merge::PushLocal));
+
+        let same = merge_remote(2, &local, None, payload(2, vec![todo(1, "Milk")]));
+        assert!(matches!(same, Merge::UpToDate));
+    }
+
+    #[test]
+    fn merge_remote_combines_equal_versions_with_different_todos() {
+        let local = todos(vec![todo(1, "Milk")]);
+        let merge = merge_remote(2, &local, None, payload(2, vec![todo(1, "Eggs")]));
+        assert!(matches!(merge, Merge::Combine(_)));
+    }
+
+    #[test]
+    fn merge_remote_combines_when_both_sides_changed_since_the_base() {
+        let base = payload(1, vec![todo(1, "Milk")]);
+        let local = todos(vec![todo(1, "Milk"), todo(2, "Bread")]);
+        let remote = payload(2, vec![todo(1, "Oat milk")]);
+        let merge = merge_remote(2, &local, Some(&base), remote);
+        assert!(matches!(merge, Merge::Combine(_)));
+
+        // Only the server changed.
+        let remote = payload(2, vec![todo(1, "Oat milk")]);
+        let merge = merge_remote(1, &base.todos, Some(&base), remote);
+        assert!(matches!(merge, Merge::TakeRemote(_)));
+    }
+
+    #[test]
+    fn csv_quotes_commas_quotes_and_line_breaks() {
+        assert_eq!(csv_field("milk"), "milk");
+        assert_eq!(csv_field("milk, eggs"), "\"milk, eggs\"");
+        assert_eq!(csv_field("the \"good\" milk"), "\"the \"\"good\"\" milk\"");
+        assert_eq!(csv_field("milk\neggs"), "\"milk\neggs\"");
+    }
+
+    #[test]
+    fn parse_csv_reads_quoted_fields() {
+        let rows = parse_csv("title,icon\r\n\"milk, eggs\",\"\"\"\"\n\"two\nlines\",x").unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["title".to_owned(), "icon".to_owned()],
+                vec!["milk, eggs".to_owned(), "\"".to_owned()],
+                vec!["two\nlines".to_owned(), "x".to_owned()],
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_csv_rejects_an_unclosed_quote() {
+        assert_eq!(
+            parse_csv("title\r\n\"milk"),
+            Err("Unclosed quote in row 2".to_owned())
+        );
+        assert!(parse_csv("\"milk\"eggs").is_err());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let mut milk = todo(1, "Milk, \"oat\"\nor soy");
+        milk.completed = true;
+        milk.points = Some(3);
+        milk.icon = Some("🥛".to_owned());
+        milk.blocked_by = vec![id(2)];
+        milk.comments = vec![(1_618_000_000_000, "Asked, \"Bob\"".to_owned())];
+        milk.checklist = vec![("Cold".to_owned(), true), ("Fresh".to_owned(), false)];
+        let mut eggs = todo(2, "Eggs");
+        eggs.backlog = true;
+        eggs.progress = 40;
+
+        let csv = to_csv(&[&milk, &eggs]);
+        let imported = from_csv(&csv, IdKind::Ulid).unwrap();
+
+        assert_eq!(imported, vec![milk, eggs]);
+    }
+
+    #[test]
+    fn from_csv_needs_only_a_title() {
+        let imported = from_csv("title,unknown\nMilk,1\n,2\nEggs,3", IdKind::Ulid).unwrap();
+        let titles: Vec<&str> = imported.iter().map(|todo| todo.title.as_str()).collect();
+        assert_eq!(titles, vec!["Milk", "Eggs"]);
+        assert!(imported[0].id != imported[1].id);
+
+        assert!(from_csv("id\n1", IdKind::Ulid).is_err());
+        assert_eq!(
+            from_csv("title,checklist\nMilk,[oops", IdKind::Ulid),
+            Err("Invalid checklist in row 2".to_owned())
+        );
+    }
+
+    #[test]
+    fn rebind_replaces_only_the_edited_combo() {
+        let rebinding = Rebinding {
+            action: Action::Undo,
+            replaced: Some("Ctrl+z".to_owned()),
+        };
+        let keymap = rebind(&BTreeMap::new(), &rebinding, "u".to_owned()).unwrap();
+        assert_eq!(keymap.get("u"), Some(&Action::Undo));
+        assert_eq!(keymap.get("Meta+z"), Some(&Action::Undo));
+        assert!(keymap.get("Ctrl+z").is_none());
+        assert_eq!(keymap.get("f"), Some(&Action::CycleFilter));
+
+        let adding = Rebinding {
+            action: Action::Undo,
+            replaced: None,
+        };
+        let keymap = rebind(&keymap, &adding, "Alt+z".to_owned()).unwrap();
+        assert_eq!(keymap.get("u"), Some(&Action::Undo));
+        assert_eq!(keymap.get("Alt+z"), Some(&Action::Undo));
+    }
+
+    #[test]
+    fn rebind_refuses_a_combo_of_another_action() {
+        let rebinding = Rebinding {
+            action: Action::Undo,
+            replaced: Some("Ctrl+z".to_owned()),
+        };
+        assert_eq!(
+            rebind(&BTreeMap::new(), &rebinding, "f".to_owned()),
+            Err(Clash::Action(Action::CycleFilter))
+        );
+        // Pressing the combo it already has is fine.
+        assert!(rebind(&BTreeMap::new(), &rebinding, "Ctrl+z".to_owned()).is_ok());
+    }
+
+    #[test]
+    fn rebind_refuses_the_first_key_of_a_sequence() {
+        let rebinding = Rebinding {
+            action: Action::Undo,
+            replaced: None,
+        };
+        assert_eq!(
+            rebind(&BTreeMap::new(), &rebinding, "g".to_owned()),
+            Err(Clash::Sequence(Sequence::FocusNewTodo))
+        );
+        // Only the first key is taken, the second one is free on its own.
+        assert!(rebind(&BTreeMap::new(), &rebinding, "n".to_owned()).is_ok());
+    }
+
+    fn titles(todos: &[Todo]) -> Vec<&str> {
+        todos.iter().map(|todo| todo.title.as_str()).collect()
+    }
+
+    #[test]
+    fn validate_import_skips_malformed_entries() {
+        let value = serde_json::json!([
+            {"title": " Milk ", "completed": true},
+            {"text": "Eggs", "done": false, "id": 7},
+            {"title": ""},
+            {"completed": true},
+            {"title": 42},
+            {"title": "Bread", "completed": "yes"},
+            "Butter",
+        ]);
+        let (todos, skipped) = validate_import(&value, IdKind::Ulid);
+
+        assert_eq!(titles(&todos), vec!["Milk", "Eggs"]);
+        assert!(todos[0].completed);
+        assert_eq!(
+            skipped,
+            vec![
+                "entry 3 has no title",
+                "entry 4 has no title",
+                "entry 5 has a title that isn't text",
+                "entry 6 isn't completed or not",
+                "entry 7 is not a todo",
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_import_keeps_valid_ids() {
+        let uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let value = serde_json::json!({"todos": [{"title": "Milk", "id": uuid}]});
+        let (todos, skipped) = validate_import(&value, IdKind::Ulid);
+        assert!(todos[0].id == TodoId::parse(uuid).unwrap());
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn validate_import_checks_exported_todos() {
+        let milk = Todo::new(TodoId::Ulid(Ulid(1)), "Milk".to_owned());
+        let eggs = Todo::new(TodoId::Ulid(Ulid(2)), "Eggs".to_owned());
+        let mut value = serde_json::json!({});
+        for todo in &[&milk, &eggs] {
+            value[todo.id.to_string()] = serde_json::to_value(todo).unwrap();
+        }
+        value[eggs.id.to_string()]["completed"] = serde_json::json!("yes");
+        value["not-an-id"] = serde_json::json!({"title": "Bread"});
 
-// (This function is invoked by `init` function in `index.html`.)
-#[wasm_bindgen(start)]
-pub fn start() {
-    console_error_panic_hook::set_once();
+        let (todos, skipped) = validate_import(&value, IdKind::Ulid);
 
-    let root_element = document()
-        .get_elements_by_class_name("todoapp")
-        .item(0)
-        .expect("Could not find .todoapp");
+        assert_eq!(titles(&todos), vec!["Milk"]);
+        assert!(todos[0].id == milk.id);
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped
+            .iter()
+            .any(|reason| reason.starts_with("todo not-an-id:")));
+    }
+
+    #[test]
+    fn validate_import_rejects_other_values() {
+        let (todos, skipped) = validate_import(&serde_json::json!("Milk"), IdKind::Ulid);
+        assert!(todos.is_empty());
+        assert_eq!(skipped, vec!["not a list of todos"]);
+    }
+
+    #[test]
+    fn skipped_summary_lists_the_first_reasons() {
+        assert_eq!(skipped_summary(&[]), "");
+        let skipped: Vec<String> = (1..=4).map(|number| format!("entry {}", number)).collect();
+        assert_eq!(
+            skipped_summary(&skipped[..1]),
+            ", skipped 1 (entry 1)".to_owned()
+        );
+        assert_eq!(
+            skipped_summary(&skipped),
+            ", skipped 4 (entry 1; entry 2; entry 3; …)".to_owned()
+        );
+    }
+
+    #[test]
+    fn parse_pasted_detects_the_format() {
+        let milk = Todo::new(TodoId::Ulid(Ulid(1)), "Milk".to_owned());
+        let exported: BTreeMap<TodoId, Todo> = vec![(milk.id, milk)].into_iter().collect();
+        let exported = serde_json::to_string(&exported).unwrap();
+        let (format, todos, _) = parse_pasted(&exported, IdKind::Ulid);
+        assert!(matches!(format, PastedFormat::Export));
+        assert_eq!(titles(&todos), vec!["Milk"]);
+
+        let (format, todos, _) = parse_pasted(r#"[{"title": "Eggs"}]"#, IdKind::Ulid);
+        assert!(matches!(format, PastedFormat::Legacy));
+        assert_eq!(titles(&todos), vec!["Eggs"]);
+
+        let (format, todos, _) = parse_pasted(r#"{"todos": [{"text": "Eggs"}]}"#, IdKind::Ulid);
+        assert!(matches!(format, PastedFormat::Legacy));
+        assert_eq!(titles(&todos), vec!["Eggs"]);
+
+        let (format, todos, _) = parse_pasted("Milk\n\n  Eggs \n42", IdKind::Ulid);
+        assert!(matches!(format, PastedFormat::Lines));
+        assert_eq!(titles(&todos), vec!["Milk", "Eggs", "42"]);
+
+        let (format, todos, _) = parse_pasted("7", IdKind::Ulid);
+        assert!(matches!(format, PastedFormat::Lines));
+        assert_eq!(titles(&todos), vec!["7"]);
+    }
+
+    #[test]
+    fn list_order_sorts_by_points_on_request() {
+        let mut small = todo(1, "Small");
+        small.points = Some(1);
+        let unpointed = todo(2, "Unpointed");
+        let mut large = todo(3, "Large");
+        large.points = Some(8);
+        let mut other_small = todo(4, "Other small");
+        other_small.points = Some(1);
+        let list = vec![&small, &unpointed, &large, &other_small];
+
+        let shown: Vec<&str> = list_order(list.clone(), false)
+            .iter()
+            .map(|todo| todo.title.as_str())
+            .collect();
+        assert_eq!(shown, vec!["Small", "Unpointed", "Large", "Other small"]);
+
+        let shown: Vec<&str> = list_order(list, true)
+            .iter()
+            .map(|todo| todo.title.as_str())
+            .collect();
+        assert_eq!(shown, vec!["Large", "Small", "Other small", "Unpointed"]);
+    }
+
+    #[test]
+    fn total_points_counts_only_active_pointed_todos() {
+        let mut active = todo(1, "Active");
+        active.points = Some(5);
+        let mut completed = todo(2, "Completed");
+        completed.points = Some(8);
+        completed.completed = true;
+        let mut backlog = todo(3, "Backlog");
+        backlog.points = Some(3);
+        backlog.backlog = true;
+        let mut other = todo(5, "Other");
+        other.points = Some(2);
+        let list = todos(vec![
+            active,
+            completed,
+            backlog,
+            todo(4, "Unpointed"),
+            other,
+        ]);
+
+        assert_eq!(total_points(&list), 7);
+        assert_eq!(total_points(&BTreeMap::new()), 0);
+    }
+
+    #[test]
+    fn voice_note_cap() {
+        assert!(fits_voice_note_cap(0));
+        assert!(fits_voice_note_cap(VOICE_NOTE_MAX_BYTES));
+        assert!(not(fits_voice_note_cap(VOICE_NOTE_MAX_BYTES + 1)));
+    }
+
+    #[test]
+    fn audio_fingerprints_compare_like_the_recordings() {
+        let recording =
+            "data:audio/webm;base64,GkXfo59ChoEBQveBAULygQRC84EIQoKEd2VibUKHgQRChYECGFOAZwH";
+        let fingerprint = audio_fingerprint(recording);
+        assert!(fingerprint.starts_with('#'));
+        assert_eq!(audio_fingerprint(&fingerprint), fingerprint);
+        assert!(audio_fingerprint("data:audio/webm;base64,AAAA") != fingerprint);
+
+        let mut milk = todo(1, "Milk");
+        milk.audio = Some(recording.into());
+        let local = todos(vec![milk.clone()]);
+        let base = fingerprint_audio(&local);
+        assert!(base[&id(1)].audio.as_deref() == Some(fingerprint.as_str()));
+        assert!(same_todos(&base, &local));
+
+        milk.audio = None;
+        assert!(not(same_todo(&base[&id(1)], &milk)));
+    }
+
+    #[test]
+    fn a_fingerprinted_base_merges_without_conflicts() {
+        let mut milk = todo(1, "Milk");
+        milk.audio = Some("data:audio/webm;base64,AAAA".into());
+        let base = fingerprint_audio(&todos(vec![milk.clone()]));
+        let local = todos(vec![milk.clone()]);
+        let mut remote_milk = milk;
+        remote_milk.title = "Oat milk".to_owned();
+        let remote = todos(vec![remote_milk.clone()]);
+
+        let (merged, conflicts) = merge_todos(&base, &local, &remote);
+
+        assert!(conflicts.is_empty());
+        assert_eq!(merged[&id(1)], remote_milk);
+
+        // Deleted remotely and unchanged locally.
+        let (merged, conflicts) = merge_todos(&base, &local, &BTreeMap::new());
+        assert!(conflicts.is_empty());
+        assert!(merged.is_empty());
+    }
+
+    // Local milliseconds of `day` (days since 1970-01-01) at `hours:minutes`.
+    fn local(day: i64, hours: i64, minutes: i64) -> i64 {
+        day * 86_400_000 + hours * 3_600_000 + minutes * 60_000
+    }
+
+    const WEDNESDAY: i64 = 18_731; // 2021-04-14
+    const SATURDAY: i64 = 18_734;
+    const SUNDAY: i64 = 18_735;
+    const MONDAY: i64 = 18_736;
+
+    #[test]
+    fn snooze_later_today_and_tomorrow_morning() {
+        let now = local(WEDNESDAY, 10, 30);
+        assert_eq!(
+            compute_snooze_time(SnoozePreset::LaterToday, now),
+            local(WEDNESDAY, 18, 0)
+        );
+        assert_eq!(
+            compute_snooze_time(SnoozePreset::TomorrowMorning, now),
+            local(WEDNESDAY + 1, 9, 0)
+        );
+        // In the evening "Later today" is in the past, the menu leaves it out.
+        let evening = local(WEDNESDAY, 20, 0);
+        assert!(compute_snooze_time(SnoozePreset::LaterToday, evening) < evening);
+    }
+
+    #[test]
+    fn snooze_this_weekend_rolls_forward() {
+        let weekend = |now| compute_snooze_time(SnoozePreset::ThisWeekend, now);
+        assert_eq!(weekend(local(WEDNESDAY, 10, 0)), local(SATURDAY, 9, 0));
+        assert_eq!(weekend(local(SATURDAY, 8, 0)), local(SATURDAY, 9, 0));
+        assert_eq!(weekend(local(SATURDAY, 10, 0)), local(SUNDAY, 9, 0));
+        assert_eq!(weekend(local(SUNDAY, 8, 59)), local(SUNDAY, 9, 0));
+        assert_eq!(weekend(local(SUNDAY, 9, 0)), local(SATURDAY + 7, 9, 0));
+    }
+
+    #[test]
+    fn snooze_next_week() {
+        let next_week = |now| compute_snooze_time(SnoozePreset::NextWeek, now);
+        assert_eq!(next_week(local(WEDNESDAY, 10, 0)), local(MONDAY, 9, 0));
+        assert_eq!(next_week(local(SUNDAY, 23, 0)), local(MONDAY, 9, 0));
+        assert_eq!(next_week(local(MONDAY, 8, 0)), local(MONDAY + 7, 9, 0));
+    }
+
+    #[test]
+    fn toggle_refusal_for_blocked_todos_and_the_wip_limit() {
+        let mut ship = todo(1, "Ship");
+        ship.blocked_by = vec![id(2)];
+        let mut done = todo(3, "Done");
+        done.completed = true;
+        let list = todos(vec![ship, todo(2, "Test"), done]);
+
+        assert_eq!(
+            toggle_refusal(&list, None, id(1)),
+            Some(ToggleRefusal::Blocked)
+        );
+        assert_eq!(toggle_refusal(&list, Some(1), id(2)), None);
+        // Two active todos, reopening a third one needs a limit of 3.
+        assert_eq!(
+            toggle_refusal(&list, Some(2), id(3)),
+            Some(ToggleRefusal::WipLimit)
+        );
+        assert_eq!(toggle_refusal(&list, Some(3), id(3)), None);
+        assert_eq!(toggle_refusal(&list, None, id(4)), None);
+    }
+
+    #[test]
+    fn comments_are_timestamped_and_listed_newest_first() {
+        let mut milk = todo(1, "Milk");
+        milk.add_comment(" Asked Bob ", 1_000);
+        milk.add_comment("   ", 2_000);
+        milk.add_comment("Bob says oat", 3_000);
+
+        assert_eq!(
+            milk.comments,
+            vec![
+                (1_000, "Asked Bob".to_owned()),
+                (3_000, "Bob says oat".to_owned())
+            ]
+        );
+        let newest_first: Vec<i64> = milk
+            .comments_newest_first()
+            .map(|(timestamp, _)| *timestamp)
+            .collect();
+        assert_eq!(newest_first, vec![3_000, 1_000]);
+    }
+
+    #[test]
+    fn merge_todos_takes_changes_from_both_sides() {
+        let base = todos(vec![todo(1, "Milk"), todo(2, "Eggs")]);
+        let mut local = base.clone();
+        local.get_mut(&id(1)).unwrap().completed = true;
+        local.insert(id(3), todo(3, "Bread"));
+        let mut remote = base.clone();
+        remote.get_mut(&id(1)).unwrap().title = "Oat milk".to_owned();
+        remote.remove(&id(2));
+        remote.insert(id(4), todo(4, "Butter"));
+
+        let (merged, conflicts) = merge_todos(&base, &local, &remote);
+
+        assert!(conflicts.is_empty());
+        let mut milk = todo(1, "Oat milk");
+        milk.completed = true;
+        assert_eq!(
+            merged,
+            todos(vec![milk, todo(3, "Bread"), todo(4, "Butter")])
+        );
+    }
+
+    #[test]
+    fn merge_todos_keeps_local_values_of_conflicting_fields() {
+        let base = todos(vec![todo(1, "Milk")]);
+        let mut local = base.clone();
+        local.get_mut(&id(1)).unwrap().title = "Soy milk".to_owned();
+        let mut remote = base.clone();
+        remote.get_mut(&id(1)).unwrap().title = "Oat milk".to_owned();
+        remote.get_mut(&id(1)).unwrap().points = Some(2);
+
+        let (merged, conflicts) = merge_todos(&base, &local, &remote);
+
+        assert_eq!(merged[&id(1)].title, "Soy milk");
+        assert_eq!(merged[&id(1)].points, Some(2));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, id(1));
+        match &conflicts[0].kind {
+            ConflictKind::Fields(fields, remote) => {
+                assert!(matches!(fields.as_slice(), [TodoField::Title]));
+                assert_eq!(remote.title, "Oat milk");
+            }
+            _ => panic!("expected a field conflict"),
+        }
+    }
+
+    #[test]
+    fn merge_todos_keeps_todos_deleted_on_one_side_and_edited_on_the_other() {
+        let base = todos(vec![todo(1, "Milk")]);
+        let edited = todos(vec![todo(1, "Oat milk")]);
+
+        let (merged, conflicts) = merge_todos(&base, &edited, &BTreeMap::new());
+        assert_eq!(merged, edited);
+        assert!(matches!(conflicts[0].kind, ConflictKind::DeletedRemotely));
+
+        let (merged, conflicts) = merge_todos(&base, &BTreeMap::new(), &edited);
+        assert_eq!(merged, edited);
+        assert!(matches!(conflicts[0].kind, ConflictKind::DeletedLocally));
+
+        // Deleted on both sides.
+        let (merged, conflicts) = merge_todos(&base, &BTreeMap::new(), &BTreeMap::new());
+        assert!(merged.is_empty() && conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_todos_without_a_base_reports_every_difference() {
+        let local = todos(vec![todo(1, "Milk")]);
+        let remote = todos(vec![todo(1, "Oat milk")]);
+        let (merged, conflicts) = merge_todos(&BTreeMap::new(), &local, &remote);
+        assert_eq!(merged, local);
+        assert_eq!(conflicts.len(), 1);
+    }
+
+    #[test]
+    fn parse_filter_ignores_case_and_trailing_slashes() {
+        assert_eq!(parse_filter(&[]), Filter::All);
+        assert_eq!(parse_filter(&["active"]), Filter::Active);
+        assert_eq!(parse_filter(&["Active", ""]), Filter::Active);
+        assert_eq!(parse_filter(&["COMPLETED", "", ""]), Filter::Completed);
+        assert_eq!(parse_filter(&["backlog"]), Filter::Backlog);
+        assert_eq!(parse_filter(&["upcoming"]), Filter::Upcoming);
+    }
+
+    #[test]
+    fn parse_filter_falls_back_to_all() {
+        assert_eq!(parse_filter(&["unknown"]), Filter::All);
+        assert_eq!(parse_filter(&["", "active"]), Filter::All);
+        assert_eq!(parse_filter(&["active", "completed"]), Filter::All);
+    }
+
+    #[test]
+    fn would_create_cycle_follows_the_dependencies() {
+        // 1 is blocked by 2, which is blocked by 3.
+        let mut first = todo(1, "First");
+        first.blocked_by = vec![id(2)];
+        let mut second = todo(2, "Second");
+        second.blocked_by = vec![id(3)];
+        let list = todos(vec![first, second, todo(3, "Third"), todo(4, "Fourth")]);
+
+        assert!(would_create_cycle(&list, id(3), id(1)));
+        assert!(would_create_cycle(&list, id(2), id(1)));
+        assert!(would_create_cycle(&list, id(1), id(1)));
+        assert!(not(would_create_cycle(&list, id(1), id(3))));
+        assert!(not(would_create_cycle(&list, id(4), id(1))));
+        // Unknown dependencies can't close a loop.
+        assert!(not(would_create_cycle(&list, id(1), id(9))));
+    }
+
+    #[test]
+    fn is_blocked_until_the_dependencies_are_completed() {
+        let mut ship = todo(1, "Ship");
+        ship.blocked_by = vec![id(2), id(3)];
+        let mut list = todos(vec![ship, todo(2, "Test")]);
+        assert!(list[&id(1)].is_blocked(&list));
+
+        list.get_mut(&id(2)).unwrap().completed = true;
+        // 3 was removed, it doesn't block anymore.
+        assert!(not(list[&id(1)].is_blocked(&list)));
+    }
+
+    #[test]
+    fn completed_today_rolls_over_on_a_new_day() {
+        let mut completed_today = CompletedToday {
+            day: Day(100),
+            count: 3,
+            goal_met: false,
+        };
+        assert!(not(completed_today.roll_over(Day(100))));
+        assert_eq!(completed_today.count, 3);
+
+        assert!(completed_today.roll_over(Day(101)));
+        assert_eq!(completed_today.day, Day(101));
+        assert_eq!(completed_today.count, 0);
+    }
+
+    #[test]
+    fn streak_extends_on_consecutive_days() {
+        let mut streak = Streak::default();
+        assert_eq!(streak.current(Day(10)), 0);
+
+        streak.record_completion(Day(10));
+        streak.record_completion(Day(10));
+        assert_eq!(streak.current(Day(10)), 1);
+        streak.record_completion(Day(11));
+        assert_eq!(streak.current(Day(11)), 2);
+        // Still alive the day after, gone the day after that.
+        assert_eq!(streak.current(Day(12)), 2);
+        assert_eq!(streak.current(Day(13)), 0);
+
+        streak.record_completion(Day(14));
+        assert_eq!(streak.current(Day(14)), 1);
+    }
+
+    #[test]
+    fn streak_starts_over_when_the_clock_goes_back() {
+        assert_eq!(update_streak(Some(Day(10)), Day(9), 5), 1);
+        assert_eq!(update_streak(None, Day(10), 0), 1);
+    }
+
+    #[test]
+    fn reach_goal_celebrates_once_a_day() {
+        let mut completed_today = CompletedToday {
+            day: Day(100),
+            count: 2,
+            goal_met: false,
+        };
+        assert!(not(completed_today.reach_goal(None)));
+        assert!(not(completed_today.reach_goal(Some(3))));
+
+        completed_today.count = 3;
+        assert!(completed_today.reach_goal(Some(3)));
+        completed_today.count = 4;
+        assert!(not(completed_today.reach_goal(Some(3))));
+
+        // A new day, a new celebration.
+        completed_today.roll_over(Day(101));
+        completed_today.count = 3;
+        assert!(completed_today.reach_goal(Some(3)));
+    }
+
+    #[test]
+    fn todos_starting_later_are_upcoming_not_active() {
+        let today = Day(100);
+        let mut later = todo(1, "Later");
+        later.start = Some(Day(101));
+        let mut started = todo(2, "Started");
+        started.start = Some(today);
+        let plain = todo(3, "Plain");
+
+        assert!(later.upcoming_on(today));
+        assert!(not(started.upcoming_on(today)));
+        assert!(not(plain.upcoming_on(today)));
+
+        let matches = |filter: Filter, todo: &Todo| filter.matches_on(todo, || today);
+        assert!(matches(Filter::Upcoming, &later) && not(matches(Filter::Active, &later)));
+        assert!(matches(Filter::Active, &started) && not(matches(Filter::Upcoming, &started)));
+        assert!(matches(Filter::Active, &plain) && not(matches(Filter::Upcoming, &plain)));
+        assert!(matches(Filter::All, &later));
+
+        // Completed todos are only Completed, whatever their start date.
+        later.completed = true;
+        assert!(not(matches(Filter::Upcoming, &later)));
+        assert!(matches(Filter::Completed, &later));
+    }
+
+    #[test]
+    fn filters_without_start_dates_never_need_the_clock() {
+        let plain = todo(1, "Plain");
+        for filter in [
+            Filter::All,
+            Filter::Active,
+            Filter::Upcoming,
+            Filter::Completed,
+        ] {
+            filter.matches_on(&plain, || panic!("the clock was read"));
+        }
+    }
+
+    #[test]
+    fn toggle_completed_visibility_round_trip() {
+        let (current, previous) = toggle_completed_visibility(Filter::Completed, Filter::All);
+        assert_eq!((current, previous), (Filter::Active, Filter::Completed));
+        let (current, previous) = toggle_completed_visibility(current, previous);
+        assert_eq!((current, previous), (Filter::Completed, Filter::Active));
+
+        // Nothing to return to after picking Active by hand.
+        assert_eq!(
+            toggle_completed_visibility(Filter::Active, Filter::Active),
+            (Filter::All, Filter::Active)
+        );
+    }
+
+    #[test]
+    fn visible_todos_leave_out_hidden_completed_todos_in_all() {
+        let mut done = todo(1, "Done");
+        done.completed = true;
+        let mut hidden = todo(2, "Hidden");
+        hidden.completed = true;
+        let mut backlog = todo(4, "Backlog");
+        backlog.backlog = true;
+        let list = todos(vec![done, hidden, todo(3, "Open"), backlog]);
+        let hidden_completed: BTreeSet<TodoId> = vec![id(2)].into_iter().collect();
+
+        let visible = |filter| -> Vec<TodoId> {
+            visible_todos(&list, filter, &hidden_completed)
+                .map(|todo| todo.id)
+                .collect()
+        };
+        assert_eq!(visible(Filter::All), vec![id(1), id(3)]);
+        // The Completed filter still lists them.
+        assert_eq!(visible(Filter::Completed), vec![id(1), id(2)]);
+        assert_eq!(visible(Filter::Active), vec![id(3)]);
+        assert_eq!(visible(Filter::Backlog), vec![id(4)]);
+    }
+
+    #[test]
+    fn history_state_round_trip() {
+        let mut milk = todo(1, "Milk");
+        milk.completed = true;
+        let cleared = todos(vec![milk, todo(2, "Eggs")]);
+        let json = serde_json::to_string(&cleared).unwrap();
+
+        assert_eq!(parse_history_state(&json), Some(cleared));
+        assert_eq!(parse_history_state("not json"), None);
+        assert_eq!(parse_history_state("null"), None);
+    }
+
+    #[test]
+    fn escape_saves_or_cancels_the_edit() {
+        assert!(matches!(escape_msg(true), Msg::SaveSelectedTodo));
+        assert!(matches!(escape_msg(false), Msg::SelectTodo(None)));
+    }
+
+    #[test]
+    fn hash_links_live_under_the_route_base() {
+        let root = Url::new();
+        assert_eq!(hash_link(&root, &[]), "#/");
+        assert_eq!(hash_link(&root, &[ACTIVE]), "#/active");
+
+        let base = Url::new().set_hash_path(["apps", "todos"]);
+        assert_eq!(hash_link(&base, &[]), "#/apps/todos");
+        assert_eq!(hash_link(&base, &[COMPLETED]), "#/apps/todos/completed");
+        assert_eq!(
+            hash_link(&base, &[TODO_LINK, "01F3ZJ"]),
+            "#/apps/todos/todo/01F3ZJ"
+        );
+    }
+
+    #[test]
+    fn related_todos_share_the_most_title_words() {
+        let target = todo(1, "Buy oat milk at market");
+        let list = todos(vec![
+            target.clone(),
+            todo(2, "Market: oat milk and eggs"),
+            todo(3, "Call the bank"),
+            todo(4, "Return the MILK bottles"),
+            todo(5, "Milk the cows at the farm"),
+            todo(6, "Go to a gig"),
+        ]);
+
+        assert_eq!(related_todos(&target, &list, 5), vec![id(2), id(4), id(5)]);
+        // Short words like "at" don't count, ties keep the list order.
+        assert_eq!(related_todos(&target, &list, 2), vec![id(2), id(4)]);
+        assert!(related_todos(&todo(7, "Go"), &list, 5).is_empty());
+    }
+
+    #[test]
+    fn key_sequence_matches_two_keys_in_time() {
+        let mut sequence = KeySequence::default();
+        assert!(matches!(sequence.press("g", 0), SequenceMatch::Partial));
+        assert!(matches!(
+            sequence.press("n", 1_000),
+            SequenceMatch::Complete(Sequence::FocusNewTodo)
+        ));
+        // The sequence is used up.
+        assert!(matches!(sequence.press("n", 1_100), SequenceMatch::NoMatch));
+    }
+
+    #[test]
+    fn key_sequence_times_out() {
+        let mut sequence = KeySequence::default();
+        sequence.press("g", 0);
+        assert!(matches!(
+            sequence.press("t", KEY_SEQUENCE_TIMEOUT_MS + 1),
+            SequenceMatch::NoMatch
+        ));
+        sequence.press("g", 0);
+        assert!(matches!(
+            sequence.press("t", KEY_SEQUENCE_TIMEOUT_MS),
+            SequenceMatch::Complete(Sequence::FocusFirstTodo)
+        ));
+    }
+
+    #[test]
+    fn key_sequence_restarts_on_a_partial_match() {
+        let mut sequence = KeySequence::default();
+        assert!(matches!(sequence.press("f", 0), SequenceMatch::NoMatch));
+        assert!(matches!(sequence.press("g", 10), SequenceMatch::Partial));
+        // A second `g` starts over instead of completing anything.
+        assert!(matches!(sequence.press("g", 20), SequenceMatch::Partial));
+        assert!(matches!(sequence.press("x", 30), SequenceMatch::NoMatch));
+        assert!(matches!(sequence.press("n", 40), SequenceMatch::NoMatch));
+    }
+
+    #[test]
+    fn split_titles_on_the_delimiter() {
+        assert_eq!(
+            split_titles(" milk; eggs ;;bread ", Some(';')),
+            vec!["milk", "eggs", "bread"]
+        );
+        assert_eq!(split_titles("milk; eggs", None), vec!["milk; eggs"]);
+        assert_eq!(split_titles("  ", Some(';')), Vec::<String>::new());
+        assert_eq!(split_titles(" ; ", Some(';')), Vec::<String>::new());
+    }
+
+    #[test]
+    fn capitalize_first_upper_cases_only_the_first_character() {
+        assert_eq!(capitalize_first("buy milk"), "Buy milk");
+        assert_eq!(capitalize_first("Buy milk"), "Buy milk");
+        assert_eq!(capitalize_first("ßtraße"), "SStraße");
+        assert_eq!(capitalize_first("🥛 milk"), "🥛 milk");
+        assert_eq!(capitalize_first(""), "");
+    }
+
+    #[test]
+    fn activate_due_takes_only_todos_whose_time_has_come() {
+        let mut scheduled: BTreeMap<TodoId, (Todo, i64)> = vec![
+            (id(1), (todo(1, "Past"), 500)),
+            (id(2), (todo(2, "Now"), 1_000)),
+            (id(3), (todo(3, "Later"), 1_001)),
+        ]
+        .into_iter()
+        .collect();
+
+        let due: Vec<TodoId> = activate_due(&mut scheduled, 1_000)
+            .iter()
+            .map(|todo| todo.id)
+            .collect();
+
+        assert_eq!(due, vec![id(1), id(2)]);
+        assert_eq!(scheduled.keys().copied().collect::<Vec<_>>(), vec![id(3)]);
+        assert!(activate_due(&mut scheduled, 1_000).is_empty());
+    }
+
+    #[test]
+    fn summarized_completed_count_counts_the_visible_completed_todos() {
+        let mut done = todo(1, "Done");
+        done.completed = true;
+        let mut also_done = todo(3, "Also done");
+        also_done.completed = true;
+        let open = todo(2, "Open");
+
+        assert_eq!(summarized_completed_count(&[&done, &open, &also_done]), 2);
+        assert_eq!(summarized_completed_count(&[&open]), 0);
+        assert_eq!(summarized_completed_count(&[]), 0);
+    }
+
+    #[test]
+    fn find_type_ahead_wraps_around_and_ignores_case() {
+        let (milk, eggs, mint) = (todo(1, "Milk"), todo(2, "eggs"), todo(3, "mint tea"));
+        let list = [&milk, &eggs, &mint];
+
+        assert_eq!(find_type_ahead(&list, "m", 0), Some(0));
+        assert_eq!(find_type_ahead(&list, "m", 1), Some(2));
+        assert_eq!(find_type_ahead(&list, "m", 3), Some(0));
+        assert_eq!(find_type_ahead(&list, "e", 2), Some(1));
+        assert_eq!(find_type_ahead(&list, "mi", 0), Some(0));
+        assert_eq!(find_type_ahead(&list, "min", 0), Some(2));
+        assert_eq!(find_type_ahead(&list, "x", 0), None);
+        assert_eq!(find_type_ahead(&[], "m", 0), None);
+    }
+
+    #[test]
+    fn todo_ids_serialize_as_plain_strings() {
+        let ulid = TodoId::Ulid(Ulid(1));
+        let json = serde_json::to_string(&ulid).unwrap();
+        assert_eq!(json, "\"00000000000000000000000001\"");
+        assert_eq!(serde_json::from_str::<TodoId>(&json).unwrap(), ulid);
+
+        let uuid = TodoId::parse("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        assert!(matches!(uuid, TodoId::Uuid(_)));
+        let json = serde_json::to_string(&uuid).unwrap();
+        assert_eq!(json, "\"67e55044-10b1-426f-9247-bb680e5fe0c8\"");
+        assert_eq!(serde_json::from_str::<TodoId>(&json).unwrap(), uuid);
+    }
+
+    #[test]
+    fn todo_ids_of_both_kinds_can_be_map_keys() {
+        let uuid = TodoId::parse("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let mut eggs = todo(2, "Eggs");
+        eggs.id = uuid;
+        let list = todos(vec![todo(1, "Milk"), eggs]);
+
+        let json = serde_json::to_string(&list).unwrap();
+        let parsed: BTreeMap<TodoId, Todo> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed, list);
+        assert_eq!(TodoId::parse("42"), None);
+    }
+
+    #[test]
+    fn backlog_todos_only_show_in_the_backlog() {
+        let mut someday = todo(1, "Someday");
+        someday.backlog = true;
+        for filter in [Filter::All, Filter::Active, Filter::Completed] {
+            assert!(not(filter.matches(&someday)));
+        }
+        assert!(Filter::Backlog.matches(&someday));
+        assert!(not(Filter::Backlog.matches(&todo(2, "Now"))));
+
+        // Completing it doesn't move it out of the backlog.
+        someday.completed = true;
+        assert!(not(Filter::Completed.matches(&someday)));
+        assert!(Filter::Backlog.matches(&someday));
+    }
+
+    #[test]
+    fn new_todos_inherit_the_filter_context() {
+        for (filter, backlog) in [
+            (Filter::All, false),
+            (Filter::Active, false),
+            (Filter::Upcoming, false),
+            (Filter::Completed, false),
+            (Filter::Backlog, true),
+        ] {
+            let mut new_todo = todo(1, "New");
+            filter.apply_to_new(&mut new_todo);
+            assert_eq!(new_todo.backlog, backlog, "{:?}", filter);
+            assert!(not(new_todo.completed) && new_todo.start.is_none());
+        }
+    }
+
+    #[test]
+    fn an_explicit_filter_in_the_url_wins_over_the_default() {
+        let root = Url::new();
+        let url = |parts: &[&str]| Url::new().set_hash_path(parts);
+
+        assert_eq!(
+            resolve_initial_filter(url(&[]), &root, Filter::Active),
+            Filter::Active
+        );
+        assert_eq!(
+            resolve_initial_filter(url(&[COMPLETED]), &root, Filter::Active),
+            Filter::Completed
+        );
+        // An explicit but unknown filter still means All.
+        assert_eq!(
+            resolve_initial_filter(url(&["unknown"]), &root, Filter::Active),
+            Filter::All
+        );
+
+        let base = url(&["todos"]);
+        assert_eq!(
+            resolve_initial_filter(url(&["todos"]), &base, Filter::Backlog),
+            Filter::Backlog
+        );
+        assert_eq!(
+            resolve_initial_filter(url(&["todos", "active"]), &base, Filter::Backlog),
+            Filter::Active
+        );
+    }
+
+    #[test]
+    fn reset_needs_the_exact_confirmation() {
+        assert!(reset_confirmed("DELETE"));
+        assert!(not(reset_confirmed("delete")));
+        assert!(not(reset_confirmed(" DELETE")));
+        assert!(not(reset_confirmed("")));
+    }
+
+    #[test]
+    fn items_left_label_is_singular_only_for_one() {
+        assert_eq!(items_left_label_for("one"), " item left");
+        assert_eq!(items_left_label_for("few"), " items left");
+        assert_eq!(items_left_label_for("other"), " items left");
+    }
+
+    #[test]
+    fn english_plural_rules_are_the_fallback() {
+        assert_eq!(english_plural_category(0), "other");
+        assert_eq!(english_plural_category(1), "one");
+        assert_eq!(english_plural_category(2), "other");
+        assert_eq!(english_plural_category(21), "other");
+    }
+
+    #[test]
+    fn pomodoro_switches_between_focus_and_break() {
+        let mut pomodoro = Pomodoro::new(id(1));
+        for _ in 1..POMODORO_FOCUS_SECS {
+            assert!(pomodoro.tick().is_none());
+        }
+        assert_eq!(pomodoro.remaining_secs, 1);
+
+        assert!(pomodoro.tick() == Some(PomodoroPhase::Focus));
+        assert!(pomodoro.phase == PomodoroPhase::Break);
+        assert_eq!(pomodoro.remaining_secs, POMODORO_BREAK_SECS);
+
+        pomodoro.remaining_secs = 1;
+        assert!(pomodoro.tick() == Some(PomodoroPhase::Break));
+        assert!(pomodoro.phase == PomodoroPhase::Focus);
+        assert_eq!(pomodoro.remaining_secs, POMODORO_FOCUS_SECS);
+    }
+
+    #[test]
+    fn todo_links_are_parsed_under_the_route_base() {
+        let milk = id(1).to_string();
+        assert_eq!(parse_todo_link(&["todo", &milk]), Some(id(1)));
+        assert_eq!(parse_todo_link(&["Todo", &milk, ""]), Some(id(1)));
+        assert_eq!(parse_todo_link(&["todo", "42"]), None);
+        assert_eq!(parse_todo_link(&["active"]), None);
+        assert_eq!(parse_todo_link(&["todo", &milk, "more"]), None);
+
+        let base = Url::new().set_hash_path(["todos"]);
+        let link = Url::new().set_hash_path(["todos", "todo", milk.as_str()]);
+        assert_eq!(todo_link_target(&link, &base), Some(id(1)));
+    }
+
+    #[test]
+    fn push_bounded_drops_the_oldest_snapshot() {
+        let mut stack = Vec::new();
+        for n in 0..HISTORY_LIMIT as u128 + 2 {
+            push_bounded(&mut stack, todos(vec![todo(n, "Snapshot")]));
+        }
+        assert_eq!(stack.len(), HISTORY_LIMIT);
+        assert!(stack[0].contains_key(&id(2)));
+        assert!(stack[HISTORY_LIMIT - 1].contains_key(&id(HISTORY_LIMIT as u128 + 1)));
+    }
+
+    // Edits `todos` like `update` does: the change is recorded with the todos before it.
+    fn edit(history: &mut History, list: &mut BTreeMap<TodoId, Todo>, title: &str) {
+        let previous = list.clone();
+        list.get_mut(&id(1)).unwrap().title = title.to_owned();
+        history.record(previous);
+    }
+
+    fn title(list: &BTreeMap<TodoId, Todo>) -> &str {
+        &list[&id(1)].title
+    }
+
+    #[test]
+    fn a_new_edit_after_undo_clears_the_redo() {
+        let mut history = History::default();
+        let mut list = todos(vec![todo(1, "Milk")]);
+        edit(&mut history, &mut list, "Oat milk");
+
+        assert!(history.undo(&mut list));
+        assert_eq!(title(&list), "Milk");
+        edit(&mut history, &mut list, "Soy milk");
+
+        assert!(not(history.redo(&mut list)));
+        assert_eq!(title(&list), "Soy milk");
+        assert!(history.undo(&mut list));
+        assert_eq!(title(&list), "Milk");
+        assert!(not(history.undo(&mut list)));
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = History::default();
+        let mut list = todos(vec![todo(1, "Milk")]);
+        edit(&mut history, &mut list, "Oat milk");
+        edit(&mut history, &mut list, "Soy milk");
+
+        for _ in 0..3 {
+            assert!(history.undo(&mut list));
+            assert_eq!(title(&list), "Oat milk");
+            assert!(history.undo(&mut list));
+            assert_eq!(title(&list), "Milk");
+            assert!(not(history.undo(&mut list)));
+
+            assert!(history.redo(&mut list));
+            assert_eq!(title(&list), "Oat milk");
+            assert!(history.redo(&mut list));
+            assert_eq!(title(&list), "Soy milk");
+            assert!(not(history.redo(&mut list)));
+        }
+    }
+
+    #[test]
+    fn activity_between_describes_the_changes() {
+        let mut done = todo(2, "Eggs");
+        let previous = todos(vec![todo(1, "Milk"), done.clone(), todo(3, "Bread")]);
+        done.completed = true;
+        let current = todos(vec![todo(1, "Oat milk"), done, todo(4, "Butter")]);
+
+        let entries: Vec<(String, TodoId, String)> = activity_between(&previous, &current, 7)
+            .into_iter()
+            .map(|entry| {
+                assert_eq!(entry.timestamp, 7);
+                let kind = match entry.kind {
+                    ActivityKind::Create => "create",
+                    ActivityKind::Complete => "complete",
+                    ActivityKind::Rename => "rename",
+                    ActivityKind::Delete => "delete",
+                };
+                (kind.to_owned(), entry.todo_id, entry.detail)
+            })
+            .collect();
+
+        let entry = |kind: &str, n, detail: &str| (kind.to_owned(), id(n), detail.to_owned());
+        assert_eq!(
+            entries,
+            vec![
+                entry("delete", 3, "Bread"),
+                entry("rename", 1, "Milk → Oat milk"),
+                entry("complete", 2, "Eggs"),
+                entry("create", 4, "Butter"),
+            ]
+        );
+        assert!(activity_between(&current, &current, 8).is_empty());
+    }
 
-    App::start(root_element, init, update, view);
+    #[test]
+    fn checklist_progress_counts_the_checked_items() {
+        let mut milk = todo(1, "Milk");
+        assert_eq!(milk.checklist_progress(), (0, 0));
+        milk.checklist = vec![
+            ("Cold".to_owned(), true),
+            ("Fresh".to_owned(), false),
+            ("Organic".to_owned(), true),
+        ];
+        assert_eq!(milk.checklist_progress(), (2, 3));
+    }
+
+    #[test]
+    fn completed_todos_are_always_done() {
+        let mut milk = todo(1, "Milk");
+        milk.progress = 40;
+        assert_eq!(milk.progress(), 40);
+        milk.completed = true;
+        assert_eq!(milk.progress(), 100);
+    }
+
+    #[test]
+    fn format_elapsed_counts_minutes_and_seconds() {
+        assert_eq!(format_elapsed(1_000, 1_000), "00:00");
+        assert_eq!(format_elapsed(1_000, 1_999), "00:00");
+        assert_eq!(format_elapsed(0, 65_000), "01:05");
+        assert_eq!(format_elapsed(0, 3_725_000), "62:05");
+        // A clock moved backwards doesn't count down.
+        assert_eq!(format_elapsed(5_000, 1_000), "00:00");
+    }
+
+    #[test]
+    fn leaving_full_screen_ends_the_distraction_shield() {
+        let mut shield = Some(DistractionShield::new(1_000));
+
+        // Entering full screen fires the event too.
+        assert!(not(sync_distraction_shield(&mut shield, true)));
+        assert_eq!(shield.as_ref().map(|shield| shield.started_at), Some(1_000));
+
+        assert!(sync_distraction_shield(&mut shield, false));
+        assert!(shield.is_none());
+        // Exiting through the button has ended it already.
+        assert!(not(sync_distraction_shield(&mut shield, false)));
+    }
+
+    #[test]
+    fn legacy_todomvc_formats_are_imported() {
+        // Vanilla JS, with numeric ids.
+        let vanilla = serde_json::json!([
+            {"id": 1618000000000_i64, "title": "Milk", "completed": false},
+            {"id": 1618000000001_i64, "title": "Eggs", "completed": true},
+        ]);
+        let (todos, skipped) = validate_import(&vanilla, IdKind::Ulid);
+        assert_eq!(titles(&todos), vec!["Milk", "Eggs"]);
+        assert_eq!(
+            todos.iter().map(|todo| todo.completed).collect::<Vec<_>>(),
+            vec![false, true]
+        );
+        assert!(todos[0].id != todos[1].id);
+        assert!(skipped.is_empty());
+
+        // `text` and `done`, wrapped in an object.
+        let wrapped = serde_json::json!({"todos": [{"text": "Bread", "done": true}]});
+        let (todos, _) = validate_import(&wrapped, IdKind::Uuid);
+        assert_eq!(titles(&todos), vec!["Bread"]);
+        assert!(todos[0].completed);
+        assert!(matches!(todos[0].id, TodoId::Uuid(_)));
+    }
+
+    #[test]
+    fn splitting_a_todo_off_respects_the_wip_limit() {
+        let mut list = todos(vec![todo(1, "Buy milk and eggs")]);
+        let split = |todos: &mut BTreeMap<TodoId, Todo>| {
+            todos.insert(id(2), todo(2, "eggs"));
+        };
+
+        assert!(not(change_within_wip_limit(&mut list, Some(1), split)));
+        assert_eq!(list.len(), 1);
+
+        assert!(change_within_wip_limit(&mut list, Some(2), split));
+        assert_eq!(
+            titles(&list.values().cloned().collect::<Vec<_>>()),
+            ["Buy milk and eggs", "eggs"]
+        );
+    }
+
+    #[test]
+    fn pulling_from_the_backlog_respects_the_wip_limit() {
+        let mut someday = todo(2, "Learn piano");
+        someday.backlog = true;
+        let mut maybe = todo(3, "Paint the fence");
+        maybe.backlog = true;
+        let mut list = todos(vec![todo(1, "Buy milk"), someday, maybe]);
+
+        // All of the pulled todos or none.
+        assert!(not(change_within_wip_limit(&mut list, Some(2), |todos| {
+            pull_from_backlog(todos, 2)
+        })));
+        assert!(list[&id(2)].backlog && list[&id(3)].backlog);
+
+        assert!(change_within_wip_limit(&mut list, Some(2), |todos| {
+            pull_from_backlog(todos, 1)
+        }));
+        assert!(not(list[&id(2)].backlog) && list[&id(3)].backlog);
+    }
+
+    #[test]
+    fn changes_without_new_active_todos_ignore_the_wip_limit() {
+        let mut list = todos(vec![todo(1, "One"), todo(2, "Two"), todo(3, "Three")]);
+
+        // Already over the limit, moving a todo to the backlog still works.
+        assert!(change_within_wip_limit(&mut list, Some(1), |todos| {
+            todos.get_mut(&id(3)).unwrap().backlog = true
+        }));
+        assert!(list[&id(3)].backlog);
+
+        let mut added = 0;
+        let mut done = todo(4, "Done");
+        done.completed = true;
+        assert!(change_within_wip_limit(&mut list, Some(1), |todos| {
+            added = add_new_todos(todos, vec![todo(1, "Taken id"), done])
+        }));
+        assert_eq!(added, 1);
+        assert_eq!(list[&id(1)].title, "One");
+    }
+
+    fn selected(n: u128, title: &str) -> SelectedTodo {
+        SelectedTodo {
+            id: id(n),
+            title: title.to_owned(),
+            input_element: ElRef::new(),
+            recording: None,
+        }
+    }
+
+    #[test]
+    fn an_edited_todo_removed_elsewhere_is_forgotten() {
+        let mut selected_todo = Some(selected(1, "Oat milk"));
+        let list = todos(vec![todo(1, "Milk"), todo(2, "Eggs")]);
+        assert!(not(forget_missing_selection(&mut selected_todo, &list)));
+        assert!(selected_todo.is_some());
+
+        // Another tab removed the todo, the storage sync loads the rest.
+        let mut list = todos(vec![todo(2, "Eggs")]);
+        assert!(forget_missing_selection(&mut selected_todo, &list));
+        assert!(selected_todo.is_none());
+
+        // Saving the stale edit doesn't bring the todo back.
+        save_edit(&mut list, &selected(1, "Oat milk"), false);
+        save_edit(&mut list, &selected(1, ""), false);
+        assert_eq!(list, todos(vec![todo(2, "Eggs")]));
+    }
+
+    #[test]
+    fn done_log_is_newest_first() {
+        let mut done_log = Vec::new();
+        assert!(log_done(&mut done_log, 1_000, "Called the bank"));
+        assert!(log_done(&mut done_log, 3_000, " Fixed the bike "));
+        // Logged on the same millisecond, the later entry goes first.
+        assert!(log_done(&mut done_log, 3_000, "Watered the plants"));
+        // A clock that went back still sorts by the timestamp.
+        assert!(log_done(&mut done_log, 2_000, "Read a chapter"));
+        assert!(not(log_done(&mut done_log, 4_000, "  ")));
+
+        assert_eq!(
+            done_log,
+            vec![
+                (3_000, "Watered the plants".to_owned()),
+                (3_000, "Fixed the bike".to_owned()),
+                (2_000, "Read a chapter".to_owned()),
+                (1_000, "Called the bank".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn older_done_logs_are_sorted_newest_first() {
+        let oldest_first = vec![(1_000, "Called".to_owned()), (2_000, "Read".to_owned())];
+        assert_eq!(
+            newest_first(oldest_first),
+            vec![(2_000, "Read".to_owned()), (1_000, "Called".to_owned())]
+        );
+    }
+
+    #[test]
+    fn focus_task_is_set_cleared_and_completed() {
+        let mut list = todos(vec![todo(1, "Write the report"), todo(2, "Call mom")]);
+
+        // Set.
+        let mut focus_task = Some(id(1));
+        assert_eq!(
+            focus_banner_todo(focus_task, &list).map(|todo| todo.id),
+            Some(id(1))
+        );
+
+        // Cleared.
+        focus_task = None;
+        assert!(focus_banner_todo(focus_task, &list).is_none());
+
+        // Completing another todo keeps it.
+        focus_task = Some(id(1));
+        assert!(not(complete_focus_task(&mut focus_task, id(2))));
+        assert_eq!(focus_task, Some(id(1)));
+
+        // Completing it ends it.
+        assert!(complete_focus_task(&mut focus_task, id(1)));
+        assert_eq!(focus_task, None);
+
+        // Completed somewhere else, the banner still goes away.
+        list.get_mut(&id(2)).unwrap().completed = true;
+        assert!(focus_banner_todo(Some(id(2)), &list).is_none());
+        list.remove(&id(1));
+        assert!(focus_banner_todo(Some(id(1)), &list).is_none());
+    }
+
+    #[test]
+    fn only_the_latest_deletion_can_be_restored() {
+        let mut list = todos(vec![todo(1, "Milk"), todo(2, "Eggs"), todo(3, "Bread")]);
+        let mut last_deleted = None;
+
+        assert!(delete_todo(&mut list, &mut last_deleted, id(1)).is_some());
+        assert!(delete_todo(&mut list, &mut last_deleted, id(2)).is_some());
+        // Nothing to delete, the slot keeps the last deletion.
+        assert!(delete_todo(&mut list, &mut last_deleted, id(1)).is_none());
+
+        let deleted = last_deleted.take().unwrap();
+        assert_eq!(deleted.todo.title, "Eggs");
+        restore_deleted(&mut list, deleted.todo);
+        assert_eq!(list, todos(vec![todo(2, "Eggs"), todo(3, "Bread")]));
+    }
+
+    #[test]
+    fn restoring_keeps_a_todo_that_took_the_id() {
+        let mut list = todos(vec![todo(1, "Milk")]);
+        restore_deleted(&mut list, todo(1, "Deleted milk"));
+        assert_eq!(list[&id(1)].title, "Milk");
+    }
+
+    #[test]
+    fn save_status_follows_the_writes() {
+        let status = SaveStatus::Idle.next(SaveEvent::Written);
+        assert!(matches!(status, SaveStatus::Idle));
+
+        // Saved, until the indicator times out.
+        let status = status.next(SaveEvent::ChangeSaved);
+        assert!(matches!(status, SaveStatus::Saved(None)));
+        let status = status.next(SaveEvent::Written);
+        assert!(matches!(status, SaveStatus::Saved(_)));
+        assert!(matches!(
+            status.next(SaveEvent::IndicatorTimedOut),
+            SaveStatus::Idle
+        ));
+
+        // An error replaces "Saved" and isn't covered by later changes.
+        let status = SaveStatus::Saved(None).next(SaveEvent::WriteFailed);
+        assert!(matches!(status, SaveStatus::Failed));
+        let status = status
+            .next(SaveEvent::ChangeSaved)
+            .next(SaveEvent::IndicatorTimedOut);
+        assert!(matches!(status, SaveStatus::Failed));
+
+        // Until the todos can be written again.
+        let status = status.next(SaveEvent::Written);
+        assert!(matches!(status, SaveStatus::Idle));
+        assert!(matches!(
+            status.next(SaveEvent::ChangeSaved),
+            SaveStatus::Saved(None)
+        ));
+    }
+
+    #[test]
+    fn numbered_lists_render_as_ordered_lists() {
+        let items = || vec![li!["Buy milk"]];
+        assert_eq!(
+            view_todo_list_element(true, items(), None).to_string(),
+            r#"<ol class="todo-list numbered"><li>Buy milk</li></ol>"#
+        );
+        assert_eq!(
+            view_todo_list_element(false, items(), None).to_string(),
+            r#"<ul class="todo-list"><li>Buy milk</li></ul>"#
+        );
+    }
+
+    #[test]
+    fn full_progress_completes_and_reopening_starts_over() {
+        let mut milk = todo(1, "Milk");
+        assert!(milk.set_progress(100));
+        assert!(milk.completed);
+        // Already done, nothing changes.
+        assert!(not(milk.set_progress(100)));
+
+        milk.toggle_completed();
+        assert!(not(milk.completed));
+        assert_eq!(milk.progress(), 0);
+
+        // Reopened by the slider, it keeps the progress it was set to.
+        milk.set_progress(100);
+        assert!(milk.set_progress(40));
+        assert!(not(milk.completed));
+        assert_eq!(milk.progress(), 40);
+
+        // Completed by the checkbox and reopened, the partial progress comes back.
+        milk.toggle_completed();
+        assert_eq!(milk.progress(), 100);
+        milk.toggle_completed();
+        assert_eq!(milk.progress(), 40);
+    }
+
+    #[test]
+    fn only_text_entry_counts_as_typing() {
+        assert!(is_text_entry("INPUT", Some("text"), false));
+        assert!(is_text_entry("INPUT", Some("date"), false));
+        assert!(is_text_entry("TEXTAREA", None, false));
+        assert!(is_text_entry("DIV", None, true));
+        assert!(not(is_text_entry("INPUT", Some("checkbox"), false)));
+        assert!(not(is_text_entry("INPUT", Some("range"), false)));
+        assert!(not(is_text_entry("BUTTON", None, false)));
+    }
 }